@@ -0,0 +1,230 @@
+//! Derive macros of struct slots for `either-slot`.
+//!
+//! See `either_slot::EitherSlot` for more information.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Data, DeriveInput, Error, Fields, Path,
+};
+
+/// The maximum count of fields, which is the maximum arity of tuple slots.
+const MAX_FIELDS: usize = 32;
+
+#[proc_macro_derive(EitherSlot, attributes(either_slot))]
+pub fn derive_either_slot(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The path to the `either_slot` crate, overridden by
+/// `#[either_slot(crate = path)]`.
+fn crate_path(input: &DeriveInput) -> syn::Result<Path> {
+    let mut path = parse_quote!(::either_slot);
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("either_slot"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                path = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `either_slot` attribute"))
+            }
+        })?;
+    }
+    Ok(path)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let krate = crate_path(&input)?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    data.fields.span(),
+                    "`EitherSlot` can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "`EitherSlot` can only be derived for structs",
+            ))
+        }
+    };
+    if fields.len() > MAX_FIELDS {
+        return Err(Error::new(
+            fields.span(),
+            format!("`EitherSlot` supports at most {MAX_FIELDS} fields"),
+        ));
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let senders = format_ident!("{name}Senders");
+    let partial = format_ident!("{name}Partial");
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let generics = &input.generics;
+
+    let field_vis = fields.iter().map(|f| &f.vis).collect::<Vec<_>>();
+    let field_names = fields
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+    let types = fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    let bindings = (0..fields.len())
+        .map(|i| format_ident!("__field{i}"))
+        .collect::<Vec<_>>();
+
+    let sender_types = (0..types.len()).map(|i| {
+        let head = &types[..i];
+        let current = types[i];
+        let tail = &types[i + 1..];
+        quote! {
+            #krate::tuple::Named<
+                #krate::tuple::Sender<(#(#head,)*), #current, (#(#tail,)*)>,
+                #partial #ty_generics,
+            >
+        }
+    });
+
+    // The bounds are higher-ranked so that they are never trivial, and thus the
+    // impl is simply absent if some field type doesn't implement `Debug`.
+    let mut debug_generics = input.generics.clone();
+    let debug_where = debug_generics.make_where_clause();
+    for ty in &types {
+        debug_where.predicates.push(parse_quote! {
+            for<'__either_slot> ::core::option::Option<#ty>: ::core::fmt::Debug
+        });
+    }
+    let (_, _, debug_where) = debug_generics.split_for_impl();
+    let partial_name = partial.to_string();
+
+    let senders_doc = format!("The senders of the fields of a [`{name}`] slot.");
+    let partial_doc = format!(
+        "The result of a [`{name}`] slot, holding the values of the fields placed by their \
+         senders."
+    );
+
+    Ok(quote! {
+        #[doc = #senders_doc]
+        #vis struct #senders #generics #where_clause {
+            #(#field_vis #field_names: #sender_types,)*
+        }
+
+        #[doc = #partial_doc]
+        #vis struct #partial #generics #where_clause {
+            #(#field_vis #field_names: ::core::option::Option<#types>,)*
+        }
+
+        impl #impl_generics ::core::fmt::Debug for #partial #ty_generics #debug_where {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_struct(#partial_name)
+                    #(.field(::core::stringify!(#field_names), &self.#field_names))*
+                    .finish()
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Create a slot of this structure, and return the senders of its
+            /// fields.
+            #vis fn senders() -> #senders #ty_generics {
+                let (#(#bindings,)*) = #krate::tuple::tuple::<(#(#types,)*)>();
+                #senders {
+                    #(#field_names: #krate::tuple::Named::new(#bindings),)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<(#(::core::option::Option<#types>,)*)>
+            for #partial #ty_generics #where_clause
+        {
+            fn from((#(#bindings,)*): (#(::core::option::Option<#types>,)*)) -> Self {
+                #partial {
+                    #(#field_names: #bindings,)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::TryFrom<#partial #ty_generics>
+            for #name #ty_generics #where_clause
+        {
+            type Error = #partial #ty_generics;
+
+            fn try_from(partial: #partial #ty_generics) -> ::core::result::Result<Self, Self::Error> {
+                match partial {
+                    #partial { #(#field_names: ::core::option::Option::Some(#field_names),)* } => {
+                        ::core::result::Result::Ok(#name { #(#field_names,)* })
+                    }
+                    partial => ::core::result::Result::Err(partial),
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        expand(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn field_count() {
+        let fields = (0..=MAX_FIELDS)
+            .map(|i| format!("f{i}: u8"))
+            .collect::<Vec<_>>();
+        let input = syn::parse_str(&format!("struct Foo {{ {} }}", fields.join(", "))).unwrap();
+        assert_eq!(error(input), "`EitherSlot` supports at most 32 fields");
+
+        let fields = (0..MAX_FIELDS)
+            .map(|i| format!("f{i}: u8"))
+            .collect::<Vec<_>>();
+        let input = syn::parse_str(&format!("struct Foo {{ {} }}", fields.join(", "))).unwrap();
+        assert!(expand(input).is_ok());
+    }
+
+    #[test]
+    fn invalid() {
+        let input = parse_quote!(
+            struct Foo(u8, u16);
+        );
+        assert!(error(input).contains("named fields"));
+
+        let input = parse_quote!(
+            enum Foo {
+                A,
+            }
+        );
+        assert!(error(input).contains("only be derived for structs"));
+
+        let input = parse_quote! {
+            #[either_slot(krate = either_slot)]
+            struct Foo { a: u8 }
+        };
+        assert_eq!(error(input), "unsupported `either_slot` attribute");
+    }
+
+    #[test]
+    fn crate_path() {
+        let input = parse_quote! {
+            #[either_slot(crate = my::either_slot)]
+            struct Foo { a: u8 }
+        };
+        let output = expand(input).unwrap().to_string();
+        assert!(output.contains("my :: either_slot :: tuple :: tuple"));
+        // Every path to the crate is overridden.
+        let count = |pat| output.matches(pat).count();
+        assert_eq!(count("either_slot ::"), count("my :: either_slot ::"));
+    }
+}
@@ -0,0 +1,205 @@
+#ifndef EITHER_SLOT_H
+#define EITHER_SLOT_H
+
+/* Generated by cbindgen from `src/ffi.rs`, do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+
+/**
+ * The result status of sending through a slot.
+ */
+typedef enum EsStatus {
+  /**
+   * The payload is placed into the slot.
+   */
+  ES_STATUS_SENT = 0,
+  /**
+   * The slot is completed, and the result is written to the output.
+   */
+  ES_STATUS_RECEIVED = 1,
+  /**
+   * The other sender is dropped, and the payload is given back untouched.
+   */
+  ES_STATUS_DISCONNECTED = 2,
+} EsStatus;
+
+/**
+ * The opaque handle of the sender of `A` of an either slot.
+ */
+typedef struct EsASender EsASender;
+
+/**
+ * The opaque handle of the senders of an array slot not taken yet.
+ */
+typedef struct EsArray EsArray;
+
+/**
+ * The opaque handle of a sender of an array slot.
+ */
+typedef struct EsArraySender EsArraySender;
+
+/**
+ * The opaque handle of the sender of `B` of an either slot.
+ */
+typedef struct EsBSender EsBSender;
+
+/**
+ * The opaque handle of the resulting iterator of an array slot.
+ */
+typedef struct EsIter EsIter;
+
+/**
+ * A type-erased payload passed through the slots.
+ *
+ * The payload must be safe to send across threads: it may be received, or
+ * destroyed by `drop`, on whichever thread completes the slot.
+ */
+typedef struct EsPayload {
+  /**
+   * The pointer to the payload data.
+   */
+  void *data;
+  /**
+   * The destructor of `data`, called if the payload is discarded by the
+   * slot. May be null.
+   */
+  void (*drop)(void*);
+} EsPayload;
+
+/**
+ * Create an either slot, and write its senders to `a` and `b`.
+ *
+ * # Safety
+ *
+ * `a` and `b` must be valid for writes.
+ */
+void es_either_new(struct EsASender **a, struct EsBSender **b);
+
+/**
+ * Send `payload` through the sender of `A`, consuming the sender.
+ *
+ * - On [`EsStatus::Sent`], the slot takes the ownership of `payload`.
+ * - On [`EsStatus::Received`], the payload of the other sender is written to
+ *   `out`, whose ownership is transferred to the caller, and `payload` is left
+ *   to the caller as well.
+ * - On [`EsStatus::Disconnected`], `payload` is left to the caller.
+ *
+ * # Safety
+ *
+ * - `sender` must be obtained from [`es_either_new`] and not consumed yet.
+ * - `out` must be valid for writes.
+ */
+enum EsStatus es_either_send_a(struct EsASender *sender,
+                               struct EsPayload payload,
+                               struct EsPayload *out);
+
+/**
+ * Send `payload` through the sender of `B`, consuming the sender.
+ *
+ * See [`es_either_send_a`] for more information.
+ *
+ * # Safety
+ *
+ * See [`es_either_send_a`].
+ */
+enum EsStatus es_either_send_b(struct EsBSender *sender,
+                               struct EsPayload payload,
+                               struct EsPayload *out);
+
+/**
+ * Drop the sender of `A`, destroying the payload of the other sender if it
+ * has been sent.
+ *
+ * # Safety
+ *
+ * `sender` must be obtained from [`es_either_new`] and not consumed yet.
+ */
+void es_either_drop_a(struct EsASender *sender);
+
+/**
+ * Drop the sender of `B`, destroying the payload of the other sender if it
+ * has been sent.
+ *
+ * # Safety
+ *
+ * `sender` must be obtained from [`es_either_new`] and not consumed yet.
+ */
+void es_either_drop_b(struct EsBSender *sender);
+
+/**
+ * Create an array slot of `n` senders, which are taken by
+ * [`es_array_sender`], or return null if `n` is zero.
+ *
+ * A null handle must not be passed to the other functions.
+ */
+struct EsArray *es_array_new(size_t n);
+
+/**
+ * Take the sender at `i` out of `array`, or return null if `i` is out of
+ * bounds or the sender is already taken.
+ *
+ * # Safety
+ *
+ * `array` must be obtained from [`es_array_new`] and not freed yet.
+ */
+struct EsArraySender *es_array_sender(struct EsArray *array, size_t i);
+
+/**
+ * Free `array`, dropping the senders not taken yet.
+ *
+ * # Safety
+ *
+ * `array` must be obtained from [`es_array_new`] and not freed yet.
+ */
+void es_array_free(struct EsArray *array);
+
+/**
+ * Send `payload` through the sender, consuming the sender.
+ *
+ * - On [`EsStatus::Sent`], the slot takes the ownership of `payload`.
+ * - On [`EsStatus::Received`], the slot is completed, and the resulting
+ *   iterator is written to `out`.
+ *
+ * # Safety
+ *
+ * - `sender` must be obtained from [`es_array_sender`] and not consumed yet.
+ * - `out` must be valid for writes.
+ */
+enum EsStatus es_array_send(struct EsArraySender *sender,
+                            struct EsPayload payload,
+                            struct EsIter **out);
+
+/**
+ * Drop the sender, leaving its element empty. If it is the last sender, the
+ * payloads in the slot are destroyed.
+ *
+ * # Safety
+ *
+ * `sender` must be obtained from [`es_array_sender`] and not consumed yet.
+ */
+void es_array_drop_sender(struct EsArraySender *sender);
+
+/**
+ * Write the next payload of `iter` to `out` and return `true`, or return
+ * `false` if the iterator is exhausted.
+ *
+ * The ownership of the payload is transferred to the caller.
+ *
+ * # Safety
+ *
+ * - `iter` must be obtained from [`es_array_send`] and not freed yet.
+ * - `out` must be valid for writes.
+ */
+bool es_iter_next(struct EsIter *iter, struct EsPayload *out);
+
+/**
+ * Free `iter`, destroying the remaining payloads.
+ *
+ * # Safety
+ *
+ * `iter` must be obtained from [`es_array_send`] and not freed yet.
+ */
+void es_iter_free(struct EsIter *iter);
+
+#endif  /* EITHER_SLOT_H */
@@ -1,39 +1,130 @@
-use alloc::vec::Vec;
+pub mod erased;
+#[cfg(feature = "alloc")]
+pub mod forward;
+#[cfg(feature = "alloc")]
+pub mod gather;
+#[cfg(feature = "alloc")]
+pub mod open;
+pub mod range;
+#[cfg(feature = "alloc")]
+pub mod reduce;
+#[cfg(feature = "alloc")]
+pub mod reply;
+
+#[cfg(feature = "alloc")]
+use alloc::{
+    boxed::Box,
+    vec::{self, Vec},
+};
 use core::{
     array,
-    iter::{self, FusedIterator, TrustedLen},
+    iter::{FusedIterator, TrustedLen},
     marker::PhantomData,
     mem::MaybeUninit,
+    ops::Range,
     ptr,
 };
 
-use crate::include::*;
+pub use self::{
+    erased::{DynSender, DynSenderIter, Local, Sendable},
+    range::{Chunks, RangeSender},
+};
+#[cfg(feature = "alloc")]
+pub use self::{
+    forward::{from_place_forwarding, from_place_forwarding_in, vec_forwarding},
+    gather::{from_place_gather, vec_gather},
+    open::open,
+    reduce::{reduce, reduce_lock_free},
+    reply::{from_place_reply, vec_reply},
+};
+use crate::{include::*, SlotSender};
 
 const MAX_COUNT: usize = isize::MAX as _;
 
+/// The shift of the arrival ticket counter in the reference count of a slot in
+/// arrival-order mode, whose lower bits hold the reference count itself.
+const TICKET_SHIFT: u32 = usize::BITS / 2;
+/// The maximum length of a slot in arrival-order mode.
+const MAX_ORDERED_COUNT: usize = (1 << TICKET_SHIFT) - 1;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The flag of an [`Element`] marking whether it holds a value.
+///
+/// This trait is sealed: [`AtomicBool`] is used by the thread-safe slots, and
+/// [`Cell<bool>`](core::cell::Cell) by the single-threaded ones in
+/// [`local`](crate::local).
+pub trait Flag: sealed::Sealed {
+    #[doc(hidden)]
+    fn new(placed: bool) -> Self;
+
+    #[doc(hidden)]
+    fn get(&self) -> bool;
+
+    #[doc(hidden)]
+    fn set(&self, placed: bool);
+}
+
+impl sealed::Sealed for AtomicBool {}
+impl Flag for AtomicBool {
+    fn new(placed: bool) -> Self {
+        AtomicBool::new(placed)
+    }
+
+    fn get(&self) -> bool {
+        self.load(Relaxed)
+    }
+
+    fn set(&self, placed: bool) {
+        self.store(placed, Relaxed)
+    }
+}
+
+impl sealed::Sealed for core::cell::Cell<bool> {}
+impl Flag for core::cell::Cell<bool> {
+    fn new(placed: bool) -> Self {
+        core::cell::Cell::new(placed)
+    }
+
+    fn get(&self) -> bool {
+        core::cell::Cell::get(self)
+    }
+
+    fn set(&self, placed: bool) {
+        core::cell::Cell::set(self, placed)
+    }
+}
+
 /// The storage of elements in the slot.
 ///
 /// The user should only use this type when constructing the type of custom
 /// storaging [`Place`]s. Only [`Default::default`] can be used to initialize
 /// this type.
+///
+/// `F` is the [`Flag`] marking whether a value is placed, which is atomic
+/// unless the element is used by a single-threaded slot.
 #[derive(Debug)]
-pub struct Element<T> {
+#[repr(C)]
+pub struct Element<T, F: Flag = AtomicBool> {
     storage: UnsafeCell<MaybeUninit<T>>,
-    placed: AtomicBool,
+    placed: F,
 }
 
-impl<T> Default for Element<T> {
+impl<T, F: Flag> Default for Element<T, F> {
     fn default() -> Self {
         Element {
             storage: UnsafeCell::new(MaybeUninit::uninit()),
-            placed: AtomicBool::new(false),
+            placed: F::new(false),
         }
     }
 }
 
-impl<T> Element<T> {
+impl<T, F: Flag> Element<T, F> {
+    #[cfg(feature = "alloc")]
     pub fn vec(count: usize) -> Vec<Self> {
-        iter::repeat_with(Default::default)
+        core::iter::repeat_with(Default::default)
             .take(count)
             .collect::<Vec<_>>()
     }
@@ -49,7 +140,46 @@ impl<T> Element<T> {
     ///   desired.
     pub(crate) unsafe fn place(&self, data: T) {
         unsafe { self.storage.with_mut(|ptr| (*ptr).write(data)) };
-        self.placed.store(true, Relaxed);
+        self.placed.set(true);
+    }
+
+    /// Initialize the value in place by `init`, which must return the reference
+    /// to the initialized storage passed to it, and return whether the value is
+    /// placed.
+    ///
+    /// If `init` panics, or returns a reference other than the one to the
+    /// storage, this element slot is left empty.
+    ///
+    /// # Safety
+    ///
+    /// See [`Element::place`].
+    pub(crate) unsafe fn place_with(
+        &self,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> bool {
+        let placed = self.storage.with_mut(|ptr| {
+            // SAFETY: This element slot holds no value, so we have the exclusive access.
+            let storage = unsafe { &mut *ptr };
+            let expected = storage.as_ptr();
+            // The only safe way to obtain a reference to the storage is to initialize
+            // it, so the pointer check suffices. Otherwise, the storage may or may not
+            // be initialized, so it can only be leaked.
+            ptr::eq(init(storage), expected)
+        });
+        self.placed.set(placed);
+        placed
+    }
+
+    /// # Safety
+    ///
+    /// - The value must not be taken during the lifetime of the returned
+    ///   reference.
+    /// - The caller must prepend an [`Acquire`] fence if atomic ordering is
+    ///   desired.
+    pub(crate) unsafe fn get(&self) -> Option<&T> {
+        self.placed
+            .get()
+            .then(|| unsafe { self.storage.with(|ptr| (*ptr).assume_init_ref()) })
     }
 
     /// # Safety
@@ -60,7 +190,7 @@ impl<T> Element<T> {
     ///   desired.
     pub(crate) unsafe fn take(&self) -> Option<T> {
         self.placed
-            .load(Relaxed)
+            .get()
             .then(|| unsafe { self.storage.with_mut(|ptr| (*ptr).assume_init_read()) })
     }
 }
@@ -73,36 +203,220 @@ impl<T> Element<T> {
 pub trait Place<T>: AsRef<[Element<T>]> {}
 impl<T, P> Place<T> for P where P: AsRef<[Element<T>]> {}
 
-struct Inner<T, P>
+/// The part of [`Inner`] independent of its place type and allocator, which is
+/// all what erased senders can access directly.
+pub(crate) struct Header<T> {
+    /// The reference count of the slot, whose upper bits hold the arrival
+    /// ticket counter in arrival-order mode.
+    count: AtomicUsize,
+    /// The arrival tickets of the elements, only allocated in arrival-order
+    /// mode.
+    #[cfg(feature = "alloc")]
+    tickets: Option<Box<[UnsafeCell<usize>]>>,
+    /// The count of the handles sharing an all-gather slot, including the
+    /// senders not consumed yet; unused by other slots.
+    #[cfg(feature = "alloc")]
+    handles: AtomicUsize,
+    vtable: VTable<T>,
+}
+
+/// The pointer to an [`Inner`] whose place type and allocator are erased.
+type Erased<T> = NonNull<Header<T>>;
+
+/// The operations of [`Inner`] depending on its place type and allocator.
+struct VTable<T> {
+    elements: unsafe fn(Erased<T>) -> NonNull<[Element<T>]>,
+    drop_in_place: unsafe fn(Erased<T>, usize),
+}
+
+impl<T> Header<T> {
+    /// # Safety
+    ///
+    /// `this` must point to the header of a valid `Inner`, and the returned
+    /// reference must not outlive it.
+    unsafe fn elements<'a>(this: NonNull<Self>) -> &'a [Element<T>] {
+        unsafe { (this.as_ref().vtable.elements)(this).as_ref() }
+    }
+
+    /// Place the value initialized by `init` into the element at `index`, and
+    /// return whether the value is placed.
+    ///
+    /// This function looks up the element through the vtable, so it is only
+    /// used by erased senders; typed ones access their place directly.
+    ///
+    /// # Safety
+    ///
+    /// 1. `this` must point to the header of a valid `Inner`.
+    /// 2. The caller must own the element at `index`, which must be less than
+    ///    the length of the slot, and call [`Header::release`] afterwards.
+    unsafe fn place_with(
+        this: NonNull<Self>,
+        index: usize,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> bool {
+        // SAFETY: See contract 2.
+        let elem = unsafe { Header::elements(this).get_unchecked(index) };
+        // SAFETY: Each sender has its ownership of one `Element` storage in its
+        // `inner`, and thus the placing is safe. Besides, the appending `Release`
+        // ordering is supplied by `Header::release_placed`.
+        unsafe { elem.place_with(init) }
+    }
+
+    /// The arrival ticket of the value placed at `index`, or `None` if the slot
+    /// is not in arrival-order mode.
+    ///
+    /// # Safety
+    ///
+    /// The value at `index` must be placed and released by
+    /// [`Header::release_placed`], and the caller must prepend an [`Acquire`]
+    /// fence if atomic ordering is desired.
+    unsafe fn ticket(&self, index: usize) -> Option<usize> {
+        #[cfg(feature = "alloc")]
+        return self.tickets.as_ref().map(|tickets| {
+            // SAFETY: See the contract.
+            tickets[index].with(|ptr| unsafe { ptr.read() })
+        });
+        #[cfg(not(feature = "alloc"))]
+        {
+            let _ = index;
+            None
+        }
+    }
+
+    /// Release a reference count of the slot without placing any value, and
+    /// return whether it is the last one, which then owns the slot.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold a reference count of the slot and must not use it
+    /// anymore, unless it is the last one.
+    unsafe fn release(this: NonNull<Self>, order: atomic::Ordering) -> bool {
+        // SAFETY: See the contract.
+        unsafe { Header::release_placed(this, 1, 0..0, order) }
+    }
+
+    /// Release `refs` reference counts of the slot after placing the values at
+    /// `placed`, and return whether they are the last ones, which then own the
+    /// slot.
+    ///
+    /// In arrival-order mode, the arrival tickets of the placed values are
+    /// taken by the same atomic operation decreasing the reference count.
+    ///
+    /// # Safety
+    ///
+    /// 1. The caller must hold `refs` reference counts of the slot and must not
+    ///    use it anymore, unless they are the last ones.
+    /// 2. The caller must own the elements at `placed`, which must be in the
+    ///    slot.
+    unsafe fn release_placed(
+        this: NonNull<Self>,
+        refs: usize,
+        placed: Range<usize>,
+        order: atomic::Ordering,
+    ) -> bool {
+        // SAFETY: See contract 1.
+        let header = unsafe { this.as_ref() };
+
+        #[cfg(feature = "alloc")]
+        let old = match &header.tickets {
+            None => header.count.fetch_sub(refs, order),
+            Some(tickets) => {
+                let mut old = header.count.load(Relaxed);
+                loop {
+                    // The tickets are written before being taken, so that they are published
+                    // along with the values by the same `Release` ordering.
+                    for (ticket, index) in (old >> TICKET_SHIFT..).zip(placed.clone()) {
+                        // SAFETY: See contract 2.
+                        tickets[index].with_mut(|ptr| unsafe { ptr.write(ticket) });
+                    }
+                    let new = old - refs + (placed.len() << TICKET_SHIFT);
+                    match header.count.compare_exchange_weak(old, new, order, Relaxed) {
+                        Ok(_) => break old & MAX_ORDERED_COUNT,
+                        Err(current) => old = current,
+                    }
+                }
+            }
+        };
+        #[cfg(not(feature = "alloc"))]
+        let old = {
+            let _ = placed;
+            header.count.fetch_sub(refs, order)
+        };
+
+        if old == refs {
+            // We use `Acquire` fence here to observe other executions of placing values.
+            atomic::fence(Acquire);
+            return true;
+        }
+        false
+    }
+}
+
+// The header is placed first, so that a pointer to `Inner` is also a pointer to
+// its header regardless of the place type and the allocator.
+#[repr(C)]
+pub(crate) struct Inner<T, P, A = DefaultAlloc>
 where
+    A: Allocator,
     P: Place<T>,
 {
-    count: AtomicUsize,
+    header: Header<T>,
     place: P,
+    /// The allocator of this structure, taken out when deallocating.
+    alloc: ManuallyDrop<A>,
     marker: PhantomData<[T]>,
 }
 
+#[cfg(feature = "alloc")]
 impl<T, P> Inner<T, P>
+where
+    P: Place<T>,
+{
+    fn new(place: P, ordered: bool) -> NonNull<Self> {
+        Self::new_in(place, ordered, Global)
+    }
+}
+
+impl<T, P, A: Allocator> Inner<T, P, A>
 where
     P: Place<T>,
 {
     const LAYOUT: Layout = Layout::new::<Self>();
 
-    fn new(place: P) -> NonNull<Self> {
+    fn new_in(place: P, ordered: bool, alloc: A) -> NonNull<Self> {
         let count = place.as_ref().len();
         assert!(
             count <= MAX_COUNT,
             "the length of the slot must not exceed `isize::MAX`"
         );
         assert!(count > 0, "the slot must not be empty");
+        assert!(
+            !ordered || count <= MAX_ORDERED_COUNT,
+            "the length of the slot in arrival-order mode must fit in half a word"
+        );
 
-        let memory = match Global.allocate(Self::LAYOUT) {
+        let memory = match alloc.allocate(Self::LAYOUT) {
             Ok(memory) => memory.cast::<Self>(),
             Err(_) => handle_alloc_error(Self::LAYOUT),
         };
         let value = Self {
-            count: AtomicUsize::new(count),
+            header: Header {
+                count: AtomicUsize::new(count),
+                #[cfg(feature = "alloc")]
+                tickets: ordered.then(|| {
+                    core::iter::repeat_with(|| UnsafeCell::new(0))
+                        .take(count)
+                        .collect()
+                }),
+                #[cfg(feature = "alloc")]
+                handles: AtomicUsize::new(0),
+                vtable: VTable {
+                    elements: Self::erased_elements,
+                    drop_in_place: Self::erased_drop_in_place,
+                },
+            },
             place,
+            alloc: ManuallyDrop::new(alloc),
             marker: PhantomData,
         };
         // SAFETY: We own this fresh uninitialized memory whose layout is the same as
@@ -111,6 +425,21 @@ where
         memory
     }
 
+    /// # Safety
+    ///
+    /// `this` must point to the header of a valid `Inner` of this type.
+    unsafe fn erased_elements(this: Erased<T>) -> NonNull<[Element<T>]> {
+        let inner = unsafe { this.cast::<Self>().as_ref() };
+        NonNull::from(inner.place.as_ref())
+    }
+
+    /// # Safety
+    ///
+    /// See [`Inner::drop_in_place`].
+    unsafe fn erased_drop_in_place(this: Erased<T>, start: usize) {
+        unsafe { Self::drop_in_place(this.cast(), start) }
+    }
+
     /// # Safety
     ///
     /// 1. `this` must own a valid `Inner` uniquely (a.k.a. no other references
@@ -122,14 +451,37 @@ where
         // SAFETY: See contract 1.
         let inner = unsafe { this.as_ref() };
 
-        for elem in inner.place.as_ref().get(start..).into_iter().flatten() {
+        let elements = inner.place.as_ref().get(start..).into_iter().flatten();
+        for (index, elem) in (start + 1..).zip(elements) {
+            // If dropping the value panics, the guard drops the remaining values and
+            // deallocates the slot during unwinding.
+            let guard = Teardown { inner: this, index };
             // SAFETY: See contract 1.
             unsafe { drop(elem.take()) }
+            mem::forget(guard);
         }
+        // SAFETY: See contract 2. The allocator is moved out before the structure is
+        // dropped, and is only dropped after the deallocation.
+        let alloc = unsafe { ManuallyDrop::take(&mut (*this.as_ptr()).alloc) };
         // SAFETY: See contract 2.
         unsafe { ptr::drop_in_place(this.as_ptr()) };
         // SAFETY: See contract 2.
-        unsafe { Global.deallocate(this.cast(), Inner::<T, P>::LAYOUT) };
+        unsafe { alloc.deallocate(this.cast(), Self::LAYOUT) };
+    }
+}
+
+/// The unwinding guard of [`Inner::drop_in_place`], which resumes the teardown
+/// from `index`.
+struct Teardown<T, P: Place<T>, A: Allocator> {
+    inner: NonNull<Inner<T, P, A>>,
+    index: usize,
+}
+
+impl<T, P: Place<T>, A: Allocator> Drop for Teardown<T, P, A> {
+    fn drop(&mut self) {
+        // SAFETY: The guard is only dropped when the teardown in progress panics, and
+        // the slot is still uniquely owned by that teardown.
+        unsafe { Inner::drop_in_place(self.inner, self.index) }
     }
 }
 
@@ -137,20 +489,21 @@ where
 ///
 /// The user can only access the slot once by this structure.
 #[derive(Debug)]
-pub struct Sender<T, P>
+pub struct Sender<T, P, A = DefaultAlloc>
 where
+    A: Allocator,
     P: Place<T>,
 {
-    inner: NonNull<Inner<T, P>>,
+    inner: NonNull<Inner<T, P, A>>,
     index: usize,
 }
 
 // SAFETY: We satisfy the contract by exposing no reference to any associated
 // function, and provide an atomic algorithm during its access or dropping
 // process, which satisfies the need of `Send`.
-unsafe impl<T: Send, P: Place<T>> Send for Sender<T, P> {}
+unsafe impl<T: Send, P: Place<T>, A: Allocator + Send> Send for Sender<T, P, A> {}
 
-impl<T, P> Sender<T, P>
+impl<T, P, A: Allocator> Sender<T, P, A>
 where
     P: Place<T>,
 {
@@ -158,101 +511,278 @@ where
     ///
     /// 1. `inner` must hold a valid immutable reference to `Inner`.
     /// 2. `start` must be less than the length of `place` in `inner`.
-    unsafe fn new(inner: NonNull<Inner<T, P>>, index: usize) -> Self {
+    unsafe fn new(inner: NonNull<Inner<T, P, A>>, index: usize) -> Self {
         Sender { inner, index }
     }
 
+    /// Convert the sender into a raw pointer to its slot and the index of its
+    /// element.
+    ///
+    /// The sender must be converted back by [`Sender::from_raw`], or it is
+    /// leaked.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "alloc")] {
+    /// use either_slot::array::{Element, Sender};
+    ///
+    /// let [s1, s2, s3] = either_slot::array::<u32, 3>();
+    /// let (raw, index) = s3.into_raw();
+    /// s1.send(1).unwrap();
+    /// s2.send(2).unwrap();
+    ///
+    /// // SAFETY: `raw` is obtained from `into_raw` and converted back only once.
+    /// let s3 = unsafe { Sender::<u32, [Element<u32>; 3]>::from_raw(raw, index) };
+    /// let iter = s3.send(3).unwrap_err();
+    /// assert_eq!(iter.collect::<Vec<_>>(), [1, 2, 3]);
+    /// # }
+    /// ```
+    pub fn into_raw(self) -> (*mut (), usize) {
+        let this = ManuallyDrop::new(self);
+        (this.inner.as_ptr().cast(), this.index)
+    }
+
+    /// Convert the raw pointer and the index back into the sender.
+    ///
+    /// # Safety
+    ///
+    /// `raw` and `index` must be obtained from [`Sender::into_raw`] of the same
+    /// type, and converted back only once.
+    pub unsafe fn from_raw(raw: *mut (), index: usize) -> Self {
+        // SAFETY: The sender is constructed by `Self::new` before.
+        unsafe { Sender::new(NonNull::new_unchecked(raw.cast()), index) }
+    }
+
     /// Place the value into the slot, or obtain the resulting iterator if no
     /// other senders exist any longer.
-    pub fn send(self, value: T) -> Result<(), SenderIter<T, P>> {
-        // SAFETY: See contract 1 in `Self::new`.
-        let inner = unsafe { self.inner.as_ref() };
-        // SAFETY: See contract 2 in `Self::new`.
-        let elem = unsafe { inner.place.as_ref().get_unchecked(self.index) };
+    pub fn send(self, value: T) -> Result<(), SenderIter<T, P, A>> {
+        self.send_in_place(|storage| storage.write(value))
+    }
 
+    /// Place the value returned by `f` into the slot, or obtain the resulting
+    /// iterator if no other senders exist any longer.
+    ///
+    /// If `f` panics, no value is placed and this sender is considered dropped.
+    pub fn send_with(self, f: impl FnOnce() -> T) -> Result<(), SenderIter<T, P, A>> {
+        self.send_in_place(|storage| storage.write(f()))
+    }
+
+    /// Initialize the value directly in the storage of the slot by `init`, or
+    /// obtain the resulting iterator if no other senders exist any longer.
+    ///
+    /// `init` must return the reference to the storage passed to it, which is
+    /// usually obtained from [`MaybeUninit::write`].
+    ///
+    /// If `init` panics, no value is placed and this sender is considered
+    /// dropped.
+    ///
+    /// If `init` returns a reference other than the one to the storage, no
+    /// value is placed either, and this sender is considered dropped as well.
+    /// Any value written by `init` is leaked.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "alloc")] {
+    /// let [s1, s2] = either_slot::array::<[u8; 4096], 2>();
+    /// s1.send_in_place(|storage| storage.write([1; 4096])).unwrap();
+    /// let iter = s2.send_with(|| [2; 4096]).unwrap_err();
+    /// assert!(iter.map(|a| a[4095]).eq([1, 2]));
+    /// # }
+    /// ```
+    pub fn send_in_place(
+        self,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> Result<(), SenderIter<T, P, A>> {
+        // SAFETY: See contracts in `Self::new`.
+        let elem = unsafe { self.inner.as_ref().place.as_ref().get_unchecked(self.index) };
         // SAFETY: Each sender has its ownership of one `Element` storage in its
         // `inner`, and thus the placing is safe. Besides, the appending `Release`
-        // ordering is supplied.
-        unsafe { elem.place(value) };
-        let fetch_sub = inner.count.fetch_sub(1, Release);
+        // ordering is supplied by `Header::release_placed`. If `init` panics, we are
+        // dropped with no value placed.
+        let placed = unsafe { elem.place_with(init) };
 
-        let pointer = self.inner;
+        let (pointer, index) = (self.inner, self.index);
         // We don't want to call the dropper anymore because it decreases the reference
         // count once more.
         mem::forget(self);
 
-        if fetch_sub == 1 {
-            // SAFETY: We use `Acquire` fence here to observe other executions of placing
-            // values. And since the reference count is now 0, we owns `inner`, so it can be
-            // handed to the iterator safely.
-            atomic::fence(Acquire);
+        // SAFETY: Since the reference count is now 0, we owns `inner`, so it can be
+        // handed to the iterator safely.
+        let placed = if placed {
+            index..index + 1
+        } else {
+            index..index
+        };
+        if unsafe { Header::<T>::release_placed(pointer.cast(), 1, placed, Release) } {
             return Err(unsafe { SenderIter::new(pointer) });
         }
         Ok(())
     }
+
+    /// Drop the sender without placing any value, and obtain the resulting
+    /// iterator if no other senders exist any longer.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn abandon(self) -> Option<SenderIter<T, P, A>> {
+        let pointer = self.inner;
+        mem::forget(self);
+
+        // SAFETY: The same as `Sender::drop`.
+        unsafe { Header::<T>::release(pointer.cast(), Relaxed) }
+            .then(|| unsafe { SenderIter::new(pointer) })
+    }
 }
 
-impl<T, P: Place<T>> Drop for Sender<T, P> {
+impl<T, P: Place<T>, A: Allocator> Drop for Sender<T, P, A> {
     fn drop(&mut self) {
-        // SAFETY: See contract 1 in `Self::new`.
-        let inner = unsafe { self.inner.as_ref() };
         // No additional ordering is used because we now have no more
         // observations/modifications to slot values, except...
-        if inner.count.fetch_sub(1, Relaxed) == 1 {
+        // SAFETY: See contract 1 in `Self::new`.
+        if unsafe { Header::<T>::release(self.inner.cast(), Relaxed) } {
             // SAFETY: ... we now owns our `inner`.
-            atomic::fence(Acquire);
             unsafe { Inner::drop_in_place(self.inner, 0) }
         }
     }
 }
 
+impl<T, P: Place<T>, A: Allocator> SlotSender for Sender<T, P, A> {
+    type Item = T;
+    type Completion = SenderIter<T, P, A>;
+
+    fn send(self, item: T) -> Result<(), SenderIter<T, P, A>> {
+        Sender::send(self, item)
+    }
+}
+
 /// The resulting iterator of values that all the senders have placed into the
 /// slot.
 ///
 /// Obtaining this structure means other senders all have been consumed or
 /// dropped, which causes the inconsistency of the count of values yielded.
 #[derive(Debug)]
-pub struct SenderIter<T, P>
+pub struct SenderIter<T, P, A = DefaultAlloc>
 where
+    A: Allocator,
     P: Place<T>,
 {
-    inner: NonNull<Inner<T, P>>,
+    inner: NonNull<Inner<T, P, A>>,
     index: usize,
 }
 
 // SAFETY: We now owns `inner`.
-unsafe impl<T: Send, P: Place<T>> Send for SenderIter<T, P> {}
+unsafe impl<T: Send, P: Place<T>, A: Allocator + Send> Send for SenderIter<T, P, A> {}
 
-impl<T, P: Place<T>> SenderIter<T, P> {
+impl<T, P: Place<T>, A: Allocator> SenderIter<T, P, A> {
     /// # Safety
     ///
     /// `inner` must owns a valid `Inner`.
-    unsafe fn new(inner: NonNull<Inner<T, P>>) -> Self {
+    unsafe fn new(inner: NonNull<Inner<T, P, A>>) -> Self {
         Self { inner, index: 0 }
     }
-}
 
-impl<T, P: Place<T>> Iterator for SenderIter<T, P> {
-    type Item = T;
-
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_ranked(&mut self) -> Option<(Option<usize>, usize, T)> {
         // SAFETY: See contract 1 in `Sender::new`.
         let inner = unsafe { self.inner.as_ref() };
 
         // `index` in the iterator is not always less than its length, so we use the
         // safe `get` to access the element storage.
         while let Some(elem) = inner.place.as_ref().get(self.index) {
+            let index = self.index;
             self.index += 1;
 
             // SAFETY: We now owns `inner`, so no atomic ordering is needed; each element is
             // only taken once since `index` is incremented at every yield.
             if let Some(data) = unsafe { elem.take() } {
-                return Some(data);
+                let rank = unsafe { inner.header.ticket(index) };
+                return Some((rank, index, data));
             }
         }
         None
     }
 
+    /// Convert the iterator into one yielding the remaining values in the order
+    /// their senders arrived.
+    ///
+    /// The arrival order is only recorded by slots created in arrival-order
+    /// mode, e.g. [`vec_ordered`]; values of other slots are yielded in index
+    /// order.
+    ///
+    /// The arrival ranks of the values are dense: a sender dropped without
+    /// placing any value takes no rank.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut senders = either_slot::array::vec_ordered(3);
+    /// let (s1, s2, s3) = (
+    ///     senders.next().unwrap(),
+    ///     senders.next().unwrap(),
+    ///     senders.next().unwrap(),
+    /// );
+    /// s3.send('c').unwrap();
+    /// s1.send('a').unwrap();
+    /// let iter = s2.send('b').unwrap_err();
+    /// assert_eq!(iter.by_arrival().collect::<String>(), "cab");
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn by_arrival(mut self) -> ByArrival<T, P, A> {
+        // SAFETY: See contract 1 in `Sender::new`.
+        let inner = unsafe { self.inner.as_ref() };
+        let rest = inner.place.as_ref().get(self.index..).unwrap_or_default();
+
+        let mut order = (self.index..)
+            .zip(rest)
+            // SAFETY: We now owns `inner`, so no atomic ordering is needed.
+            .filter(|&(_, elem)| unsafe { elem.get() }.is_some())
+            .map(|(index, _)| (unsafe { inner.header.ticket(index) }, index))
+            .collect::<Vec<_>>();
+        order.sort_by_key(|&(rank, _)| rank);
+
+        // The remaining values are now dropped by the new iterator.
+        self.index = inner.place.as_ref().len();
+        ByArrival {
+            iter: self,
+            order: order.into_iter(),
+        }
+    }
+
+    /// Convert the iterator into one yielding the remaining values in index
+    /// order, alongside their arrival ranks and indices, in the form of
+    /// `(arrival_rank, index, value)`.
+    ///
+    /// The arrival ranks are `None` if the slot is not in arrival-order mode.
+    /// See [`SenderIter::by_arrival`] for more information about arrival
+    /// ranks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "alloc")] {
+    /// let [s1, s2] = either_slot::array();
+    /// s2.send('b').unwrap();
+    /// let iter = s1.send('a').unwrap_err();
+    /// assert!(iter.ranked().eq([(None, 0, 'a'), (None, 1, 'b')]));
+    ///
+    /// let mut senders = either_slot::array::vec_ordered(2);
+    /// let (s1, s2) = (senders.next().unwrap(), senders.next().unwrap());
+    /// s2.send('b').unwrap();
+    /// let iter = s1.send('a').unwrap_err();
+    /// assert!(iter.ranked().eq([(Some(1), 0, 'a'), (Some(0), 1, 'b')]));
+    /// # }
+    /// ```
+    pub fn ranked(self) -> Ranked<T, P, A> {
+        Ranked(self)
+    }
+}
+
+impl<T, P: Place<T>, A: Allocator> Iterator for SenderIter<T, P, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ranked().map(|(_, _, data)| data)
+    }
+
     fn size_hint(&self) -> (usize, Option<usize>) {
         // SAFETY: See contract 1 in `Sender::new`.
         let inner = unsafe { self.inner.as_ref() };
@@ -261,9 +791,9 @@ impl<T, P: Place<T>> Iterator for SenderIter<T, P> {
     }
 }
 
-impl<T, P: Place<T>> FusedIterator for SenderIter<T, P> {}
+impl<T, P: Place<T>, A: Allocator> FusedIterator for SenderIter<T, P, A> {}
 
-impl<T, P: Place<T>> Drop for SenderIter<T, P> {
+impl<T, P: Place<T>, A: Allocator> Drop for SenderIter<T, P, A> {
     fn drop(&mut self) {
         // SAFETY: We now owns `inner`, so no atomic ordering is needed; `index` is
         // always equal or less then the length of `place`.
@@ -271,6 +801,82 @@ impl<T, P: Place<T>> Drop for SenderIter<T, P> {
     }
 }
 
+/// The resulting iterator of values in arrival order.
+///
+/// See [`SenderIter::by_arrival`] for more information.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct ByArrival<T, P: Place<T>, A: Allocator = DefaultAlloc> {
+    iter: SenderIter<T, P, A>,
+    order: vec::IntoIter<(Option<usize>, usize)>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T, P: Place<T>, A: Allocator> Iterator for ByArrival<T, P, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: See contract 1 in `Sender::new`.
+        let inner = unsafe { self.iter.inner.as_ref() };
+        let (_, index) = self.order.next()?;
+        // SAFETY: We now owns `inner`, so no atomic ordering is needed; each element is
+        // only taken once since it is removed from `order`.
+        unsafe { inner.place.as_ref().get_unchecked(index).take() }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.order.size_hint()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, P: Place<T>, A: Allocator> ExactSizeIterator for ByArrival<T, P, A> {}
+
+#[cfg(feature = "alloc")]
+impl<T, P: Place<T>, A: Allocator> FusedIterator for ByArrival<T, P, A> {}
+
+#[cfg(feature = "alloc")]
+impl<T, P: Place<T>, A: Allocator> Drop for ByArrival<T, P, A> {
+    fn drop(&mut self) {
+        /// The unwinding guard dropping the remaining values.
+        struct Rest<'a, T, P: Place<T>, A: Allocator>(&'a mut ByArrival<T, P, A>);
+
+        impl<T, P: Place<T>, A: Allocator> Drop for Rest<'_, T, P, A> {
+            fn drop(&mut self) {
+                self.0.for_each(drop)
+            }
+        }
+
+        while let Some(value) = self.next() {
+            // If dropping the value panics, the guard drops the remaining values during
+            // unwinding.
+            let guard = Rest(self);
+            drop(value);
+            mem::forget(guard);
+        }
+    }
+}
+
+/// The resulting iterator of values alongside their arrival ranks and indices.
+///
+/// See [`SenderIter::ranked`] for more information.
+#[derive(Debug)]
+pub struct Ranked<T, P: Place<T>, A: Allocator = DefaultAlloc>(SenderIter<T, P, A>);
+
+impl<T, P: Place<T>, A: Allocator> Iterator for Ranked<T, P, A> {
+    type Item = (Option<usize>, usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_ranked()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T, P: Place<T>, A: Allocator> FusedIterator for Ranked<T, P, A> {}
+
 /// The initialization iterator for senders.
 ///
 /// The senders are ALREADY initialized upon the construction of this iterator.
@@ -280,24 +886,24 @@ impl<T, P: Place<T>> Drop for SenderIter<T, P> {
 /// When the iterator is dropped, it will drop all the senders yet to be
 /// yielded.
 #[derive(Debug)]
-pub struct InitIter<T, P: Place<T>> {
-    inner: NonNull<Inner<T, P>>,
+pub struct InitIter<T, P: Place<T>, A: Allocator = DefaultAlloc> {
+    inner: NonNull<Inner<T, P, A>>,
     index: usize,
 }
 
-unsafe impl<T: Send, P: Place<T>> Send for InitIter<T, P> {}
+unsafe impl<T: Send, P: Place<T>, A: Allocator + Send> Send for InitIter<T, P, A> {}
 
-impl<T, P: Place<T>> InitIter<T, P> {
+impl<T, P: Place<T>, A: Allocator> InitIter<T, P, A> {
     /// # Safety
     ///
     /// `inner` must owns a valid `Inner`.
-    unsafe fn new(inner: NonNull<Inner<T, P>>) -> Self {
+    unsafe fn new(inner: NonNull<Inner<T, P, A>>) -> Self {
         InitIter { inner, index: 0 }
     }
 }
 
-impl<T, P: Place<T>> Iterator for InitIter<T, P> {
-    type Item = Sender<T, P>;
+impl<T, P: Place<T>, A: Allocator> Iterator for InitIter<T, P, A> {
+    type Item = Sender<T, P, A>;
 
     fn next(&mut self) -> Option<Self::Item> {
         // SAFETY: See contract 1 in `Sender::new`.
@@ -316,37 +922,74 @@ impl<T, P: Place<T>> Iterator for InitIter<T, P> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         // SAFETY: See contract 1 in `Sender::new`.
         let inner = unsafe { self.inner.as_ref() };
-        let len = inner.place.as_ref().len();
+        let len = inner.place.as_ref().len() - self.index;
         (len, Some(len))
     }
 }
 
-impl<T, P: Place<T>> Drop for InitIter<T, P> {
+impl<T, P: Place<T>, A: Allocator> Drop for InitIter<T, P, A> {
     fn drop(&mut self) {
         self.for_each(drop)
     }
 }
 
-impl<T, P: Place<T>> ExactSizeIterator for InitIter<T, P> {}
+impl<T, P: Place<T>, A: Allocator> ExactSizeIterator for InitIter<T, P, A> {}
 
-impl<T, P: Place<T>> FusedIterator for InitIter<T, P> {}
+impl<T, P: Place<T>, A: Allocator> FusedIterator for InitIter<T, P, A> {}
 
-unsafe impl<T, P: Place<T>> TrustedLen for InitIter<T, P> {}
+unsafe impl<T, P: Place<T>, A: Allocator> TrustedLen for InitIter<T, P, A> {}
 
 /// Construct an iterator of senders to a slot, whose values will be placed on
 /// `place`.
+#[cfg(feature = "alloc")]
 pub fn from_place<T, P: Place<T>>(place: P) -> InitIter<T, P> {
-    let inner = Inner::new(place);
+    let inner = Inner::new(place, false);
+    // SAFETY: `inner` owns `Inner`.
+    unsafe { InitIter::new(inner) }
+}
+
+/// Construct an iterator of senders to a slot allocated in `alloc`, whose
+/// values will be placed on `place`.
+pub fn from_place_in<T, P: Place<T>, A: Allocator>(place: P, alloc: A) -> InitIter<T, P, A> {
+    let inner = Inner::new_in(place, false, alloc);
     // SAFETY: `inner` owns `Inner`.
     unsafe { InitIter::new(inner) }
 }
 
 /// Construct an iterator of senders to a slot, whose values will be placed on a
 /// [`Vec`].
+#[cfg(feature = "alloc")]
 pub fn vec<T>(count: usize) -> InitIter<T, Vec<Element<T>>> {
     from_place(Element::vec(count))
 }
 
+/// Construct an iterator of senders to a slot in arrival-order mode, whose
+/// values will be placed on `place`.
+///
+/// Each sender takes an arrival ticket when sending its value, which can be
+/// observed from [`SenderIter::by_arrival`] and [`SenderIter::ranked`]. The
+/// tickets are stored in a side table allocated only in this mode.
+///
+/// # Panics
+///
+/// Panics if the length of `place` does not fit in half a word, i.e.
+/// 2<sup>32</sup> - 1 on 64-bit targets.
+#[cfg(feature = "alloc")]
+pub fn from_place_ordered<T, P: Place<T>>(place: P) -> InitIter<T, P> {
+    let inner = Inner::new(place, true);
+    // SAFETY: `inner` owns `Inner`.
+    unsafe { InitIter::new(inner) }
+}
+
+/// Construct an iterator of senders to a slot in arrival-order mode, whose
+/// values will be placed on a [`Vec`].
+///
+/// See [`from_place_ordered`] for more information.
+#[cfg(feature = "alloc")]
+pub fn vec_ordered<T>(count: usize) -> InitIter<T, Vec<Element<T>>> {
+    from_place_ordered(Element::vec(count))
+}
+
 /// Construct an array of senders to a slot, whose values will be placed on an
 /// array.
 ///
@@ -370,13 +1013,22 @@ pub fn vec<T>(count: usize) -> InitIter<T, Vec<Element<T>>> {
 /// let iter = s2.send(2).unwrap_err();
 /// assert_eq!(iter.collect::<Vec<_>>(), [2, 3]);
 /// ```
+#[cfg(feature = "alloc")]
 pub fn array<T, const N: usize>() -> [Sender<T, [Element<T>; N]>; N] {
-    let inner = Inner::new(Element::array());
+    array_in(Global)
+}
+
+/// Construct an array of senders to a slot allocated in `alloc`, whose values
+/// will be placed on an array.
+///
+/// See [`array()`] for more information.
+pub fn array_in<T, A: Allocator, const N: usize>(alloc: A) -> [Sender<T, [Element<T>; N], A>; N] {
+    let inner = Inner::new_in(Element::array(), false, alloc);
     // SAFETY: `inner` is immutable; index is in (0..N).
     array::from_fn(move |index| unsafe { Sender::new(inner, index) })
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use alloc::vec::Vec;
     #[cfg(not(loom))]
@@ -385,7 +1037,7 @@ mod tests {
     #[cfg(loom)]
     use loom::thread;
 
-    use crate::array::{from_place, Element};
+    use crate::array::{from_place, vec_ordered, Element};
 
     #[test]
     fn send() {
@@ -410,6 +1062,56 @@ mod tests {
         loom::model(inner);
     }
 
+    #[test]
+    fn erased() {
+        fn inner() {
+            let [s1, s2] = crate::array::<alloc::string::String, 2>().map(|s| s.erase_send());
+            let j = thread::spawn(move || s1.send("a".into()));
+            // The values are dropped through the vtable if the last sender is dropped.
+            drop(s2);
+
+            if let Err(iter) = j.join().unwrap() {
+                assert_eq!(iter.collect::<Vec<_>>(), ["a"]);
+            }
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn erased_local() {
+        use core::{
+            alloc::{AllocError, Allocator, Layout},
+            marker::PhantomData,
+            ptr::NonNull,
+        };
+
+        use crate::include::Global;
+
+        /// An allocator which must stay on the current thread.
+        struct Local(PhantomData<*mut ()>);
+
+        unsafe impl Allocator for Local {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                unsafe { Global.deallocate(ptr, layout) }
+            }
+        }
+
+        let [s1, s2] = crate::array::array_in::<char, _, 2>(Local(PhantomData));
+        let [s1, s2] = [s1.erase(), s2.erase()];
+        s1.send('a').unwrap();
+        let iter = s2.send('b').unwrap_err();
+        assert_eq!(iter.collect::<Vec<_>>(), ['a', 'b']);
+    }
+
     #[test]
     fn drop_one() {
         fn inner() {
@@ -442,4 +1144,140 @@ mod tests {
         #[cfg(loom)]
         loom::model(inner);
     }
+
+    #[test]
+    fn ordered() {
+        fn inner() {
+            let j = vec_ordered(3)
+                .enumerate()
+                .map(|(i, s)| thread::spawn(move || s.send(i)))
+                .collect::<Vec<_>>();
+
+            let iter = j
+                .into_iter()
+                .map(|j| j.join().unwrap())
+                .fold(Ok(()), Result::and)
+                .unwrap_err();
+
+            let mut ranked = iter.ranked().collect::<Vec<_>>();
+            assert!(ranked.iter().all(|&(_, index, value)| index == value));
+
+            ranked.sort_by_key(|&(rank, ..)| rank);
+            assert!(ranked
+                .iter()
+                .enumerate()
+                .all(|(i, &(rank, ..))| rank == Some(i)));
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn raw() {
+        use super::Sender;
+
+        let mut senders = crate::vec::<char>(3);
+        let (s1, s2, s3) = (
+            senders.next().unwrap(),
+            senders.next().unwrap(),
+            senders.next().unwrap(),
+        );
+        let raw = [s1.into_raw(), s3.into_raw()];
+        s2.send('b').unwrap();
+        let [s1, s3] = raw.map(|(raw, index)| unsafe {
+            Sender::<char, Vec<Element<char>>>::from_raw(raw, index)
+        });
+        drop(s1);
+        let iter = s3.send('c').unwrap_err();
+        assert_eq!(
+            iter.ranked().collect::<Vec<_>>(),
+            [(None, 1, 'b'), (None, 2, 'c')]
+        );
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn ordered_drop() {
+        let mut senders = vec_ordered(3);
+        let [s1, s2, s3] = core::array::from_fn(|_| senders.next().unwrap());
+        drop(s2);
+        // Erased senders take arrival tickets as well.
+        s3.erase().send('c').unwrap();
+        let iter = s1.send('a').unwrap_err();
+        assert!(iter.ranked().eq([(Some(1), 0, 'a'), (Some(0), 2, 'c')]));
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn send_with_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut senders = vec_ordered(4);
+        let [s1, s2, s3, s4] = core::array::from_fn(|_| senders.next().unwrap());
+        let res = panic::catch_unwind(AssertUnwindSafe(|| s1.send_with(|| panic!())));
+        assert!(res.is_err());
+
+        // A foreign reference leaves the element empty in every build.
+        let res = s2.send_in_place(|_| alloc::boxed::Box::leak(alloc::boxed::Box::new(2)));
+        assert!(res.is_ok());
+
+        // Neither of the senders above takes an arrival rank.
+        s3.send(3).unwrap();
+        let iter = s4.send(4).unwrap_err();
+        assert!(iter.ranked().eq([(Some(0), 2, 3), (Some(1), 3, 4)]));
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn drop_panic() {
+        use std::{
+            panic::{self, AssertUnwindSafe},
+            sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        };
+
+        use crate::bomb::Bomb;
+
+        let dropped = AtomicUsize::new(0);
+        let [s1, s2, s3, s4] = crate::array();
+        s1.send(Bomb(false, &dropped)).unwrap();
+        s2.send(Bomb(true, &dropped)).unwrap();
+        s3.send(Bomb(false, &dropped)).unwrap();
+        let res = panic::catch_unwind(AssertUnwindSafe(move || drop(s4)));
+        assert!(res.is_err());
+        assert_eq!(dropped.load(Relaxed), 3);
+
+        let dropped = AtomicUsize::new(0);
+        let [s1, s2, s3] = crate::array();
+        s1.send(Bomb(false, &dropped)).unwrap();
+        s2.send(Bomb(true, &dropped)).unwrap();
+        let mut iter = s3.send(Bomb(false, &dropped)).unwrap_err();
+        drop(iter.next());
+        let res = panic::catch_unwind(AssertUnwindSafe(move || drop(iter)));
+        assert!(res.is_err());
+        assert_eq!(dropped.load(Relaxed), 3);
+
+        let dropped = AtomicUsize::new(0);
+        let [s1, s2, s3] = crate::array();
+        s1.send(Bomb(false, &dropped)).unwrap();
+        s2.send(Bomb(true, &dropped)).unwrap();
+        let mut ranked = s3.send(Bomb(false, &dropped)).unwrap_err().ranked();
+        drop(ranked.next());
+        let res = panic::catch_unwind(AssertUnwindSafe(move || drop(ranked)));
+        assert!(res.is_err());
+        assert_eq!(dropped.load(Relaxed), 3);
+
+        let dropped = AtomicUsize::new(0);
+        let mut senders = vec_ordered(3);
+        let [s1, s2, s3] = core::array::from_fn(|_| senders.next().unwrap());
+        s3.send(Bomb(false, &dropped)).unwrap();
+        s1.send(Bomb(true, &dropped)).unwrap();
+        let iter = s2.send(Bomb(false, &dropped)).unwrap_err().by_arrival();
+        let res = panic::catch_unwind(AssertUnwindSafe(move || drop(iter)));
+        assert!(res.is_err());
+        assert_eq!(dropped.load(Relaxed), 3);
+    }
 }
@@ -0,0 +1,246 @@
+//! Senders and iterators of array slots whose place type and allocator are
+//! erased.
+
+use core::{iter::FusedIterator, marker::PhantomData, mem::MaybeUninit};
+
+use super::{Header, Place, Sender, SenderIter};
+use crate::{include::*, SlotSender};
+
+/// The placer of an array slot, whose place type and allocator are erased.
+///
+/// Senders of slots with different place types, e.g. from
+/// [`array`](crate::array()), [`vec`](crate::vec()) and
+/// [`from_place`](super::from_place), can be stored together once erased by
+/// [`Sender::erase`], which requires no allocation.
+///
+/// The place type and the allocator may borrow data for `'a`, e.g. a
+/// [`Scope`](crate::Scope) or an [`Intrusive`](crate::intrusive::Intrusive)
+/// storage, which the erased sender must not outlive.
+///
+/// Since the place type and the allocator are no longer known, whether the
+/// erased sender may be sent to other threads is recorded by the marker `M`
+/// instead: senders erased by [`Sender::erase`] are [`Local`], while those
+/// erased by [`Sender::erase_send`] are [`Sendable`].
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// use either_slot::array::DynSender;
+///
+/// let [s1, s2] = either_slot::array::<u32, 2>();
+/// let mut s3 = either_slot::vec::<u32>(1);
+/// let senders: Vec<DynSender<u32>> = vec![s1.erase(), s2.erase(), s3.next().unwrap().erase()];
+///
+/// let mut results = senders
+///     .into_iter()
+///     .enumerate()
+///     .filter_map(|(i, s)| s.send(i as u32).err());
+/// assert_eq!(results.next().unwrap().collect::<Vec<_>>(), [0, 1]);
+/// assert_eq!(results.next().unwrap().collect::<Vec<_>>(), [2]);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DynSender<'a, T, M = Local> {
+    inner: NonNull<Header<T>>,
+    index: usize,
+    marker: PhantomData<(T, &'a (), M)>,
+}
+
+/// The marker of erased senders and iterators which must stay on the current
+/// thread.
+#[derive(Debug)]
+pub struct Local(PhantomData<*mut ()>);
+
+/// The marker of erased senders and iterators which may be sent to other
+/// threads.
+#[derive(Debug)]
+pub struct Sendable(());
+
+// SAFETY: The same as `Sender`, which is required to be `Send` upon erasure.
+unsafe impl<T: Send> Send for DynSender<'_, T, Sendable> {}
+
+impl<'a, T, P, A> Sender<T, P, A>
+where
+    P: Place<T> + 'a,
+    A: Allocator + 'a,
+{
+    /// Erase the place type and the allocator of the sender.
+    ///
+    /// The erased sender stays on the current thread; see
+    /// [`erase_send`](Self::erase_send) for one which doesn't.
+    ///
+    /// See [`DynSender`] for more information.
+    pub fn erase(self) -> DynSender<'a, T> {
+        self.erase_with()
+    }
+
+    /// Erase the place type and the allocator of the sender, keeping it able
+    /// to be sent to other threads.
+    ///
+    /// See [`DynSender`] for more information.
+    pub fn erase_send(self) -> DynSender<'a, T, Sendable>
+    where
+        Self: Send,
+    {
+        self.erase_with()
+    }
+
+    fn erase_with<M>(self) -> DynSender<'a, T, M> {
+        let this = ManuallyDrop::new(self);
+        DynSender {
+            inner: this.inner.cast(),
+            index: this.index,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, M> DynSender<'a, T, M> {
+    /// Place the value into the slot, or obtain the resulting iterator if no
+    /// other senders exist any longer.
+    pub fn send(self, value: T) -> Result<(), DynSenderIter<'a, T, M>> {
+        self.send_in_place(|storage| storage.write(value))
+    }
+
+    /// Place the value returned by `f` into the slot, or obtain the resulting
+    /// iterator if no other senders exist any longer.
+    ///
+    /// If `f` panics, no value is placed and this sender is considered dropped.
+    pub fn send_with(self, f: impl FnOnce() -> T) -> Result<(), DynSenderIter<'a, T, M>> {
+        self.send_in_place(|storage| storage.write(f()))
+    }
+
+    /// Initialize the value directly in the storage of the slot by `init`, or
+    /// obtain the resulting iterator if no other senders exist any longer.
+    ///
+    /// See [`Sender::send_in_place`] for more information.
+    pub fn send_in_place(
+        self,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> Result<(), DynSenderIter<'a, T, M>> {
+        // SAFETY: The same as `Sender::send_in_place`.
+        let placed = unsafe { Header::place_with(self.inner, self.index, init) };
+
+        let (pointer, index) = (self.inner, self.index);
+        mem::forget(self);
+
+        // SAFETY: The same as `Sender::send_in_place`.
+        let placed = if placed {
+            index..index + 1
+        } else {
+            index..index
+        };
+        if unsafe { Header::release_placed(pointer, 1, placed, Release) } {
+            return Err(DynSenderIter {
+                inner: pointer,
+                index: 0,
+                marker: PhantomData,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<T, M> Drop for DynSender<'_, T, M> {
+    fn drop(&mut self) {
+        // SAFETY: The same as `Sender::drop`.
+        if unsafe { Header::release(self.inner, Relaxed) } {
+            drop(DynSenderIter::<T, M> {
+                inner: self.inner,
+                index: 0,
+                marker: PhantomData,
+            })
+        }
+    }
+}
+
+impl<'a, T, M> SlotSender for DynSender<'a, T, M> {
+    type Item = T;
+    type Completion = DynSenderIter<'a, T, M>;
+
+    fn send(self, item: T) -> Result<(), DynSenderIter<'a, T, M>> {
+        DynSender::send(self, item)
+    }
+}
+
+/// The resulting iterator of values of an array slot, whose place type and
+/// allocator are erased.
+///
+/// See [`SenderIter`] and [`DynSender`] for more information.
+#[derive(Debug)]
+pub struct DynSenderIter<'a, T, M = Local> {
+    inner: NonNull<Header<T>>,
+    index: usize,
+    marker: PhantomData<(T, &'a (), M)>,
+}
+
+// SAFETY: We now owns `inner`, whose iterator is required to be `Send` upon
+// erasure.
+unsafe impl<T: Send> Send for DynSenderIter<'_, T, Sendable> {}
+
+impl<'a, T, P, A> SenderIter<T, P, A>
+where
+    P: Place<T> + 'a,
+    A: Allocator + 'a,
+{
+    /// Erase the place type and the allocator of the iterator.
+    ///
+    /// The erased iterator stays on the current thread; see
+    /// [`erase_send`](Self::erase_send) for one which doesn't.
+    pub fn erase(self) -> DynSenderIter<'a, T> {
+        self.erase_with()
+    }
+
+    /// Erase the place type and the allocator of the iterator, keeping it able
+    /// to be sent to other threads.
+    pub fn erase_send(self) -> DynSenderIter<'a, T, Sendable>
+    where
+        Self: Send,
+    {
+        self.erase_with()
+    }
+
+    fn erase_with<M>(self) -> DynSenderIter<'a, T, M> {
+        let this = ManuallyDrop::new(self);
+        DynSenderIter {
+            inner: this.inner.cast(),
+            index: this.index,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, M> Iterator for DynSenderIter<'_, T, M> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: We now owns `inner`.
+        let elements = unsafe { Header::elements(self.inner) };
+        while let Some(elem) = elements.get(self.index) {
+            self.index += 1;
+            // SAFETY: We now owns `inner`, so no atomic ordering is needed; each element is
+            // only taken once since `index` is incremented at every yield.
+            if let Some(data) = unsafe { elem.take() } {
+                return Some(data);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // SAFETY: We now owns `inner`.
+        let len = unsafe { Header::elements(self.inner) }.len();
+        (0, Some(len.saturating_sub(self.index)))
+    }
+}
+
+impl<T, M> FusedIterator for DynSenderIter<'_, T, M> {}
+
+impl<T, M> Drop for DynSenderIter<'_, T, M> {
+    fn drop(&mut self) {
+        // SAFETY: We now owns `inner`; `index` is always equal or less then the length
+        // of `place`.
+        unsafe { (self.inner.as_ref().vtable.drop_in_place)(self.inner, self.index) }
+    }
+}
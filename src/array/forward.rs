@@ -0,0 +1,322 @@
+//! Array slots whose resulting iterator is forwarded into a sender of a parent
+//! slot.
+
+use alloc::vec::Vec;
+use core::iter::{FusedIterator, TrustedLen};
+
+use super::{from_place_in, Element, Place, Sender, SenderIter};
+use crate::{
+    forward::{Abandon, Forward, Hook, Keep},
+    include::*,
+};
+
+impl<T, P: Place<T>, A: Allocator> Abandon for Sender<T, P, A> {
+    fn abandon(self) -> Option<SenderIter<T, P, A>> {
+        Sender::abandon(self)
+    }
+}
+
+/// The initialization iterator for senders of a forwarding slot.
+///
+/// See [`InitIter`](super::InitIter) for more information.
+#[derive(Debug)]
+pub struct InitIter<T, P, Parent, F, A = DefaultAlloc>
+where
+    P: Place<T>,
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, P, A>) -> Parent::Item,
+    A: Allocator,
+{
+    iter: super::InitIter<T, P, A>,
+    hook: NonNull<Hook<Parent, F, A>>,
+}
+
+// SAFETY: The same as `Forward`.
+unsafe impl<T, P, Parent, F, A> Send for InitIter<T, P, Parent, F, A>
+where
+    T: Send,
+    P: Place<T> + Send,
+    Parent: Keep + Send,
+    F: FnOnce(SenderIter<T, P, A>) -> Parent::Item + Send,
+    A: Allocator + Send,
+{
+}
+
+impl<T, P, Parent, F, A> Iterator for InitIter<T, P, Parent, F, A>
+where
+    P: Place<T>,
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, P, A>) -> Parent::Item,
+    A: Allocator,
+{
+    type Item = Forward<Sender<T, P, A>, Parent, F, A>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sender = self.iter.next()?;
+        // SAFETY: `hook` is shared by the senders of this slot only.
+        Some(unsafe { Forward::new(sender, self.hook) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, P, Parent, F, A> Drop for InitIter<T, P, Parent, F, A>
+where
+    P: Place<T>,
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, P, A>) -> Parent::Item,
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        // The remaining senders are wrapped before being dropped, so that the
+        // completion is still forwarded.
+        self.for_each(drop)
+    }
+}
+
+impl<T, P, Parent, F, A> ExactSizeIterator for InitIter<T, P, Parent, F, A>
+where
+    P: Place<T>,
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, P, A>) -> Parent::Item,
+    A: Allocator,
+{
+}
+
+impl<T, P, Parent, F, A> FusedIterator for InitIter<T, P, Parent, F, A>
+where
+    P: Place<T>,
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, P, A>) -> Parent::Item,
+    A: Allocator,
+{
+}
+
+unsafe impl<T, P, Parent, F, A> TrustedLen for InitIter<T, P, Parent, F, A>
+where
+    P: Place<T>,
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, P, A>) -> Parent::Item,
+    A: Allocator,
+{
+}
+
+/// Construct an iterator of senders to a slot, whose values will be placed on
+/// `place`, and whose resulting iterator will be mapped by `map` and sent by
+/// `parent`.
+///
+/// The forwarding is done by the sender completing the slot, whether it sends
+/// its value or is dropped. See [`Forward`] for more information.
+///
+/// The parents lead to a [`Root`](crate::forward::Root) created by
+/// [`forward::root`](crate::forward::root), which keeps the completion of the
+/// root slot if a dropped sender completes it.
+///
+/// # Examples
+///
+/// ```rust
+/// let [root, other] = either_slot::array::<u32, 2>();
+/// let (root, _orphan) = either_slot::forward::root(root);
+/// let mut leaves = either_slot::array::from_place_forwarding(
+///     either_slot::array::Element::vec(3),
+///     root,
+///     |iter| iter.sum::<u32>(),
+/// );
+/// let [l1, l2, l3] = std::array::from_fn(|_| leaves.next().unwrap());
+///
+/// l1.send(1).unwrap();
+/// l2.send(2).unwrap();
+/// // The last leaf is dropped, but the sum is still sent to the root.
+/// drop(l3);
+/// let iter = other.send(10).unwrap_err();
+/// assert_eq!(iter.collect::<Vec<_>>(), [3, 10]);
+/// ```
+pub fn from_place_forwarding<T, P, Parent, F>(
+    place: P,
+    parent: Parent,
+    map: F,
+) -> InitIter<T, P, Parent, F>
+where
+    P: Place<T>,
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, P>) -> Parent::Item,
+{
+    from_place_forwarding_in(place, parent, map, Global)
+}
+
+/// Construct an iterator of senders to a forwarding slot, whose values will be
+/// placed on `place`, and whose storage and hook are allocated in `alloc`.
+///
+/// See [`from_place_forwarding`] for more information.
+pub fn from_place_forwarding_in<T, P, Parent, F, A>(
+    place: P,
+    parent: Parent,
+    map: F,
+    alloc: A,
+) -> InitIter<T, P, Parent, F, A>
+where
+    P: Place<T>,
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, P, A>) -> Parent::Item,
+    A: Allocator + Clone,
+{
+    let iter = from_place_in(place, alloc.clone());
+    let hook = Hook::new_in(parent, map, alloc);
+    InitIter { iter, hook }
+}
+
+/// Construct an iterator of senders to a forwarding slot, whose values will be
+/// placed on a [`Vec`].
+///
+/// See [`from_place_forwarding`] for more information.
+pub fn vec_forwarding<T, Parent, F>(
+    count: usize,
+    parent: Parent,
+    map: F,
+) -> InitIter<T, Vec<Element<T>>, Parent, F>
+where
+    Parent: Keep,
+    F: FnOnce(SenderIter<T, Vec<Element<T>>>) -> Parent::Item,
+{
+    from_place_forwarding(Element::vec(count), parent, map)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    #[cfg(not(loom))]
+    use std::thread;
+
+    #[cfg(loom)]
+    use loom::thread;
+
+    use super::vec_forwarding;
+    use crate::forward;
+
+    #[test]
+    fn send() {
+        fn inner() {
+            let [root, other] = crate::array::<usize, 2>();
+            let (root, _orphan) = forward::root(root);
+            let mut leaves = vec_forwarding::<usize, _, _>(2, root, |iter| iter.sum());
+            let l1 = leaves.next().unwrap();
+            drop(leaves);
+            let j = thread::spawn(move || l1.send(1));
+
+            let r1 = other.send(10);
+            let r2 = j.join().unwrap();
+            let iter = r1.and(r2).unwrap_err();
+            assert_eq!(iter.collect::<Vec<_>>(), [1, 10]);
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn nested() {
+        let [root, other] = crate::array::<usize, 2>();
+        let (root, _orphan) = forward::root(root);
+        let mut mid = vec_forwarding::<usize, _, _>(2, root, |iter| iter.count());
+        let mut leaves = vec_forwarding::<usize, _, _>(3, mid.next().unwrap(), |iter| iter.sum());
+        drop(mid);
+        drop(leaves.next());
+        let l2 = leaves.next().unwrap();
+        drop(leaves);
+        l2.send(5).unwrap();
+        let iter = other.send(0).unwrap_err();
+        assert_eq!(iter.collect::<Vec<_>>(), [1, 0]);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn abandon() {
+        use crate::forward::Abandon;
+
+        let [root, other] = crate::array::<usize, 2>();
+        let (root, mut orphan) = forward::root(root);
+        let mut leaves = vec_forwarding::<usize, _, _>(2, root, |iter| iter.sum());
+        let (l1, l2) = (leaves.next().unwrap(), leaves.next().unwrap());
+        other.send(10).unwrap();
+        l1.send(1).unwrap();
+        // Abandoning `l2` returns the completion of the root slot instead of keeping
+        // it for the orphan.
+        let iter = l2
+            .abandon()
+            .expect("the last leaf should complete the root");
+        assert_eq!(iter.collect::<Vec<_>>(), [1, 10]);
+        assert!(orphan.take().is_none());
+    }
+
+    #[test]
+    fn orphan() {
+        fn inner() {
+            let [root, other] = crate::array::<usize, 2>();
+            let (root, mut orphan) = forward::root(root);
+            let mut leaves = vec_forwarding::<usize, _, _>(2, root, |iter| iter.sum());
+            let l1 = leaves.next().unwrap();
+            drop(leaves);
+            other.send(10).unwrap();
+            assert!(orphan.take().is_none());
+
+            // The last leaf is dropped on another thread, completing the root slot.
+            thread::spawn(move || drop(l1)).join().unwrap();
+            let iter = orphan.take().expect("the root completion should be kept");
+            assert_eq!(iter.collect::<Vec<_>>(), [0, 10]);
+            assert!(orphan.take().is_none());
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn orphan_dropped() {
+        use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        use crate::bomb::Bomb;
+
+        let drops = AtomicUsize::new(0);
+        let [root, other] = crate::array::<Bomb, 2>();
+        let (root, orphan) = forward::root(root);
+        let mut leaves = vec_forwarding::<usize, _, _>(1, root, |_| Bomb(false, &drops));
+        let l1 = leaves.next().unwrap();
+        drop(orphan);
+        other.send(Bomb(false, &drops)).unwrap();
+        // Without the orphan, the kept completion is dropped along with the shelf.
+        drop(l1);
+        assert_eq!(drops.load(Relaxed), 2);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn pooled() {
+        use crate::{
+            array::{from_place_forwarding_in, Element},
+            pool::Pool,
+        };
+
+        let pool = Pool::new();
+        let [root, other] = crate::array::<usize, 2>();
+        let (root, _orphan) = forward::root(root);
+        let mut leaves =
+            from_place_forwarding_in(Element::vec(2), root, |iter| iter.sum::<usize>(), &pool);
+        let (l1, l2) = (leaves.next().unwrap(), leaves.next().unwrap());
+        drop(leaves);
+        l1.send(1).unwrap();
+        l2.send(2).unwrap();
+        // Both the slot and the hook are returned to the pool.
+        assert_eq!(pool.cached(), 2);
+
+        let iter = other.send(10).unwrap_err();
+        assert_eq!(iter.collect::<Vec<_>>(), [3, 10]);
+    }
+}
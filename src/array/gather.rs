@@ -0,0 +1,380 @@
+//! All-gather array slots, whose senders all get a shared view of the
+//! completed slot.
+
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    iter::{FusedIterator, TrustedLen},
+    slice,
+};
+
+use super::{from_place, Element, Inner, Place};
+use crate::include::*;
+
+/// Release a handle reference of the slot, and drop the slot if it is the last
+/// one.
+///
+/// # Safety
+///
+/// 1. The caller must hold a handle reference of `inner`.
+/// 2. The caller must not use `inner` again since the reference is consumed in
+///    this function.
+unsafe fn release<T, P: Place<T>>(inner: NonNull<Inner<T, P>>) {
+    // SAFETY: See contract 1.
+    let header = unsafe { &inner.as_ref().header };
+    if header.handles.fetch_sub(1, Release) == 1 {
+        atomic::fence(Acquire);
+        // Every sender releases its reference count before its handle reference, so
+        // the slot is always completed here.
+        debug_assert_eq!(header.count.load(Relaxed), 0);
+        // SAFETY: We now own the slot; see contract 2.
+        unsafe { Inner::drop_in_place(inner, 0) }
+    }
+}
+
+/// The placer of an all-gather slot.
+///
+/// The user can only access the slot once by this structure.
+///
+/// This sender doesn't implement [`SlotSender`](crate::SlotSender), since
+/// sending always yields a [`Gathered`] handle instead of simply placing the
+/// value.
+#[derive(Debug)]
+pub struct Sender<T, P: Place<T>> {
+    sender: ManuallyDrop<super::Sender<T, P>>,
+}
+
+// SAFETY: Every sender gets a shared view of all the values, so `T` must be
+// `Sync` as well.
+unsafe impl<T: Send + Sync, P: Place<T>> Send for Sender<T, P> {}
+
+impl<T, P: Place<T>> Sender<T, P> {
+    /// Place the value into the slot, and obtain the shared handle of the
+    /// slot, which resolves once all the other senders are consumed or
+    /// dropped.
+    pub fn send(self, value: T) -> Gathered<T, P> {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again, so does the sender.
+        let sender = unsafe { ManuallyDrop::take(&mut this.sender) };
+        let inner = sender.inner;
+
+        if let Err(iter) = sender.send(value) {
+            // The values are kept in the slot for the views, and dropped when the last
+            // handle is released.
+            mem::forget(iter);
+        }
+        // Our handle reference is handed to the handle.
+        Gathered { inner }
+    }
+}
+
+impl<T, P: Place<T>> Drop for Sender<T, P> {
+    fn drop(&mut self) {
+        // SAFETY: `self.sender` is never used again.
+        let sender = unsafe { ManuallyDrop::take(&mut self.sender) };
+        let inner = sender.inner;
+        if let Some(iter) = sender.abandon() {
+            // The same as `Sender::send`.
+            mem::forget(iter);
+        }
+        // SAFETY: We hold a handle reference of `inner`.
+        unsafe { release(inner) }
+    }
+}
+
+/// The shared handle of a completed (or to be completed) all-gather slot.
+///
+/// The values in the slot are dropped and the storage is freed when the last
+/// handle is dropped.
+pub struct Gathered<T, P: Place<T>> {
+    inner: NonNull<Inner<T, P>>,
+}
+
+// SAFETY: The handle is a shared reference to the values.
+unsafe impl<T: Send + Sync, P: Place<T>> Send for Gathered<T, P> {}
+unsafe impl<T: Send + Sync, P: Place<T>> Sync for Gathered<T, P> {}
+
+impl<T, P: Place<T>> Gathered<T, P> {
+    fn inner(&self) -> &Inner<T, P> {
+        // SAFETY: `inner` is valid since we hold a handle reference of it.
+        unsafe { self.inner.as_ref() }
+    }
+
+    /// Check if all the senders are consumed or dropped.
+    pub fn is_complete(&self) -> bool {
+        self.inner().header.count.load(Relaxed) == 0
+    }
+
+    /// Obtain the view of the slot without blocking, if it is completed.
+    pub fn try_view(&self) -> Option<View<'_, T>> {
+        let inner = self.inner();
+        // All-gather slots are never in arrival-order mode, so the reference count
+        // reaches 0 exactly when the slot completes. Every sender decreases it by a
+        // read-modify-write operation, so observing the final 0 by `Acquire`
+        // synchronizes with all the placing senders.
+        (inner.header.count.load(Acquire) == 0).then(|| View {
+            elements: inner.place.as_ref(),
+        })
+    }
+
+    /// Spin until the slot is completed, and obtain its view.
+    ///
+    /// Since this crate is `no_std`, this function cannot park the thread.
+    pub fn wait(&self) -> View<'_, T> {
+        loop {
+            match self.try_view() {
+                Some(view) => break view,
+                None => hint::spin_loop(),
+            }
+        }
+    }
+}
+
+impl<T, P: Place<T>> Clone for Gathered<T, P> {
+    fn clone(&self) -> Self {
+        // The same as `Arc::clone`; the creation of new references requires no
+        // ordering.
+        self.inner().header.handles.fetch_add(1, Relaxed);
+        Gathered { inner: self.inner }
+    }
+}
+
+impl<T, P: Place<T>> fmt::Debug for Gathered<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gathered")
+            .field("complete", &self.is_complete())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, P: Place<T>> Drop for Gathered<T, P> {
+    fn drop(&mut self) {
+        // SAFETY: We hold a handle reference of `inner`.
+        unsafe { release(self.inner) }
+    }
+}
+
+/// The view of a completed all-gather slot, where each element is either the
+/// value placed by its sender, or [`None`] if the sender was dropped.
+#[derive(Clone, Copy)]
+pub struct View<'a, T> {
+    elements: &'a [Element<T>],
+}
+
+impl<'a, T> View<'a, T> {
+    /// The count of the elements in the slot.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Check if the slot has no elements, which is always `false`.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Obtain the value placed by the sender of `index`.
+    pub fn get(&self, index: usize) -> Option<&'a T> {
+        // SAFETY: The slot is completed and immutable, and no value is taken until the
+        // storage is dropped.
+        self.elements
+            .get(index)
+            .and_then(|elem| unsafe { elem.get() })
+    }
+
+    /// Iterate over the elements of the slot in index order.
+    pub fn iter(&self) -> Iter<'a, T> {
+        Iter(self.elements.iter())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for View<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for View<'a, T> {
+    type Item = Option<&'a T>;
+
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The iterator over the elements of a [`View`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a, T>(slice::Iter<'a, Element<T>>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = Option<&'a T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: See `View::get`.
+        self.0.next().map(|elem| unsafe { elem.get() })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        // SAFETY: See `View::get`.
+        self.0.next_back().map(|elem| unsafe { elem.get() })
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+/// The initialization iterator for senders of an all-gather slot.
+///
+/// See [`InitIter`](super::InitIter) for more information.
+#[derive(Debug)]
+pub struct InitIter<T, P: Place<T>> {
+    iter: super::InitIter<T, P>,
+}
+
+unsafe impl<T: Send + Sync, P: Place<T>> Send for InitIter<T, P> {}
+
+impl<T, P: Place<T>> Iterator for InitIter<T, P> {
+    type Item = Sender<T, P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sender = self.iter.next()?;
+        Some(Sender {
+            sender: ManuallyDrop::new(sender),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, P: Place<T>> Drop for InitIter<T, P> {
+    fn drop(&mut self) {
+        self.for_each(drop)
+    }
+}
+
+impl<T, P: Place<T>> ExactSizeIterator for InitIter<T, P> {}
+
+impl<T, P: Place<T>> FusedIterator for InitIter<T, P> {}
+
+unsafe impl<T, P: Place<T>> TrustedLen for InitIter<T, P> {}
+
+/// Construct an iterator of senders to an all-gather slot, whose values will be
+/// placed on `place`.
+///
+/// Every sender gets a [`Gathered`] handle from [`send`](Sender::send), which
+/// resolves to a shared [`View`] of all the values once the slot is completed.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut senders = either_slot::array::vec_gather(3);
+/// let [s1, s2, s3] = std::array::from_fn(|_| senders.next().unwrap());
+///
+/// let g1 = s1.send(1);
+/// assert!(g1.try_view().is_none());
+/// drop(s2);
+/// let g3 = s3.send(3);
+///
+/// let view = g1.wait();
+/// assert_eq!(view.iter().collect::<Vec<_>>(), [Some(&1), None, Some(&3)]);
+/// assert_eq!(g3.wait().get(2), Some(&3));
+/// ```
+pub fn from_place_gather<T, P: Place<T>>(place: P) -> InitIter<T, P> {
+    let iter = from_place(place);
+    // SAFETY: The slot is not shared yet. Each sender holds a handle reference,
+    // which is handed to its handle or released when it is dropped.
+    let header = unsafe { &iter.inner.as_ref().header };
+    header.handles.store(iter.len(), Relaxed);
+    InitIter { iter }
+}
+
+/// Construct an iterator of senders to an all-gather slot, whose values will be
+/// placed on a [`Vec`].
+///
+/// See [`from_place_gather`] for more information.
+pub fn vec_gather<T>(count: usize) -> InitIter<T, Vec<Element<T>>> {
+    from_place_gather(Element::vec(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    #[cfg(not(loom))]
+    use std::thread;
+
+    #[cfg(loom)]
+    use loom::thread;
+
+    use super::vec_gather;
+
+    #[test]
+    fn send() {
+        fn inner() {
+            let mut senders = vec_gather(2);
+            let (s1, s2) = (senders.next().unwrap(), senders.next().unwrap());
+            let j = thread::spawn(move || s1.send(1));
+
+            let g2 = s2.send(2);
+            let g1 = j.join().unwrap();
+
+            let expected = [Some(&1), Some(&2)];
+            assert_eq!(g1.wait().iter().collect::<Vec<_>>(), expected);
+            assert_eq!(g2.wait().iter().collect::<Vec<_>>(), expected);
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[test]
+    fn drop_one() {
+        fn inner() {
+            let mut senders = vec_gather(2);
+            let (s1, s2) = (senders.next().unwrap(), senders.next().unwrap());
+            let j = thread::spawn(move || drop(s2));
+
+            let g1 = s1.send(1);
+            j.join().unwrap();
+            let g2 = g1.clone();
+            drop(g1);
+            assert_eq!(g2.wait().iter().collect::<Vec<_>>(), [Some(&1), None]);
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn drop_panic() {
+        use std::{
+            panic::{self, AssertUnwindSafe},
+            sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        };
+
+        use crate::bomb::Bomb;
+
+        let dropped = AtomicUsize::new(0);
+        let mut senders = vec_gather(3);
+        let [s1, s2, s3] = core::array::from_fn(|_| senders.next().unwrap());
+        drop(s1.send(Bomb(true, &dropped)));
+        drop(s2.send(Bomb(false, &dropped)));
+        let res = panic::catch_unwind(AssertUnwindSafe(move || drop(s3)));
+        assert!(res.is_err());
+        assert_eq!(dropped.load(Relaxed), 2);
+    }
+}
@@ -0,0 +1,451 @@
+//! Open-ended array slots, whose participants can be registered after the
+//! creation of the slot.
+
+use core::{array, iter::FusedIterator, ptr};
+
+use super::{Element, MAX_COUNT};
+use crate::{include::*, SlotSender};
+
+/// The length of the first segment is `1 << FIRST_SHIFT`, and the length of
+/// each following segment doubles.
+const FIRST_SHIFT: u32 = 3;
+const SEGMENTS: usize = (usize::BITS - FIRST_SHIFT) as usize;
+
+/// Locate the segment and the offset in it of `index`.
+fn locate(index: usize) -> (usize, usize) {
+    let biased = index + (1 << FIRST_SHIFT);
+    let shift = usize::BITS - 1 - biased.leading_zeros();
+    ((shift - FIRST_SHIFT) as usize, biased - (1 << shift))
+}
+
+fn segment_layout<T>(segment: usize) -> Layout {
+    match Layout::array::<Element<T>>(1 << (segment as u32 + FIRST_SHIFT)) {
+        Ok(layout) => layout,
+        Err(_) => panic!("the length of the slot must not exceed `isize::MAX`"),
+    }
+}
+
+struct Inner<T> {
+    count: AtomicUsize,
+    len: AtomicUsize,
+    segments: [AtomicPtr<Element<T>>; SEGMENTS],
+}
+
+impl<T> Inner<T> {
+    const LAYOUT: Layout = Layout::new::<Self>();
+
+    fn new(count: usize) -> NonNull<Self> {
+        let memory = match Global.allocate(Self::LAYOUT) {
+            Ok(memory) => memory.cast::<Self>(),
+            Err(_) => handle_alloc_error(Self::LAYOUT),
+        };
+        let value = Self {
+            count: AtomicUsize::new(count),
+            len: AtomicUsize::new(0),
+            segments: array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+        };
+        // SAFETY: We own this fresh uninitialized memory whose layout is the same as
+        // this type.
+        unsafe { memory.as_ptr().write(value) }
+        memory
+    }
+
+    /// Register a new participant, and return its index.
+    ///
+    /// The caller must hold a reference count of the slot.
+    fn register(&self) -> usize {
+        // The same as `Arc::clone`; the creation of new references requires no
+        // ordering.
+        let old = self.count.fetch_add(1, Relaxed);
+        assert!(old < MAX_COUNT, "the count of senders overflowed");
+
+        let index = self.len.fetch_add(1, Relaxed);
+        assert!(index < MAX_COUNT, "the length of the slot overflowed");
+
+        let (segment, _) = locate(index);
+        if self.segments[segment].load(Acquire).is_null() {
+            self.allocate(segment);
+        }
+        index
+    }
+
+    #[cold]
+    fn allocate(&self, segment: usize) {
+        let layout = segment_layout::<T>(segment);
+        let memory = match Global.allocate(layout) {
+            Ok(memory) => memory.cast::<Element<T>>(),
+            Err(_) => handle_alloc_error(layout),
+        };
+        for offset in 0..(1 << (segment as u32 + FIRST_SHIFT)) {
+            // SAFETY: We own this fresh uninitialized memory whose layout is the same as
+            // an array of elements.
+            unsafe { memory.as_ptr().add(offset).write(Element::default()) }
+        }
+
+        // Another participant may be allocating the same segment at the same time.
+        let publish = self.segments[segment].compare_exchange(
+            ptr::null_mut(),
+            memory.as_ptr(),
+            AcqRel,
+            Acquire,
+        );
+        if publish.is_err() {
+            // SAFETY: The memory is never published, so it holds no values.
+            unsafe { Self::free_segment(memory, segment) }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `memory` must be a segment of `segment` not used by any other
+    /// participants, and all the values in it must be taken.
+    unsafe fn free_segment(memory: NonNull<Element<T>>, segment: usize) {
+        let len = 1 << (segment as u32 + FIRST_SHIFT);
+        // SAFETY: See the contract.
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(memory.as_ptr(), len));
+            Global.deallocate(memory.cast(), segment_layout::<T>(segment));
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `index` must be registered.
+    unsafe fn element(&self, index: usize) -> &Element<T> {
+        let (segment, offset) = locate(index);
+        let memory = self.segments[segment].load(Acquire);
+        // SAFETY: The segment is allocated upon registration.
+        unsafe { &*memory.add(offset) }
+    }
+
+    /// # Safety
+    ///
+    /// 1. `this` must own a valid `Inner` uniquely (a.k.a. no other references
+    ///    to the structure), and all the values in it must be taken.
+    /// 2. The caller must not use `this` again since it is consumed and dropped
+    ///    in this function.
+    unsafe fn drop_in_place(this: NonNull<Self>) {
+        // SAFETY: See contract 1.
+        let inner = unsafe { this.as_ref() };
+
+        for (segment, memory) in inner.segments.iter().enumerate() {
+            if let Some(memory) = NonNull::new(memory.load(Relaxed)) {
+                // SAFETY: See contract 1.
+                unsafe { Self::free_segment(memory, segment) }
+            }
+        }
+        // SAFETY: See contract 2.
+        unsafe { ptr::drop_in_place(this.as_ptr()) };
+        // SAFETY: See contract 2.
+        unsafe { Global.deallocate(this.cast(), Self::LAYOUT) };
+    }
+
+    /// Release a reference count of the slot, and obtain the resulting
+    /// iterator if it is the last one.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold a reference count of the slot and must not use it
+    /// anymore.
+    unsafe fn release(this: NonNull<Self>, order: atomic::Ordering) -> Option<SenderIter<T>> {
+        // SAFETY: See the contract.
+        let inner = unsafe { this.as_ref() };
+        (inner.count.fetch_sub(1, order) == 1).then(|| {
+            // SAFETY: We use `Acquire` fence here to observe other executions of placing
+            // values. And since the reference count is now 0, we owns `inner`, so it can be
+            // handed to the iterator safely.
+            atomic::fence(Acquire);
+            SenderIter {
+                inner: this,
+                index: 0,
+            }
+        })
+    }
+}
+
+/// The placer of an open-ended slot.
+///
+/// The user can only access the slot once by this structure, and can register
+/// new senders by [`fork`](Sender::fork)ing it.
+#[derive(Debug)]
+pub struct Sender<T> {
+    inner: NonNull<Inner<T>>,
+    index: usize,
+}
+
+// SAFETY: We satisfy the contract by exposing no reference to any associated
+// function, and provide an atomic algorithm during its access or dropping
+// process, which satisfies the need of `Send`.
+unsafe impl<T: Send> Send for Sender<T> {}
+
+impl<T> Sender<T> {
+    /// The index of the value this sender places in the slot.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Register a new sender to the slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count of the senders exceeds `isize::MAX`.
+    pub fn fork(&self) -> Sender<T> {
+        // SAFETY: `inner` is valid since we hold a reference count of it.
+        let index = unsafe { self.inner.as_ref() }.register();
+        Sender {
+            inner: self.inner,
+            index,
+        }
+    }
+
+    /// Place the value into the slot, or obtain the resulting iterator if no
+    /// other senders exist any longer and the slot is sealed.
+    pub fn send(self, value: T) -> Result<(), SenderIter<T>> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `inner` is valid since we hold a reference count of it.
+        let inner = unsafe { this.inner.as_ref() };
+        // SAFETY: Our index is registered, and each sender has its ownership of one
+        // `Element` storage in its `inner`, and thus the placing is safe. Besides, the
+        // appending `Release` ordering is supplied.
+        unsafe { inner.element(this.index).place(value) };
+
+        // SAFETY: `this` is never used again.
+        match unsafe { Inner::release(this.inner, Release) } {
+            Some(iter) => Err(iter),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        // No additional ordering is used because we now have no more
+        // observations/modifications to slot values.
+        //
+        // SAFETY: `self` is never used again.
+        drop(unsafe { Inner::release(self.inner, Relaxed) })
+    }
+}
+
+impl<T> SlotSender for Sender<T> {
+    type Item = T;
+    type Completion = SenderIter<T>;
+
+    fn send(self, item: T) -> Result<(), SenderIter<T>> {
+        Sender::send(self, item)
+    }
+}
+
+/// The seal of an open-ended slot.
+///
+/// The slot cannot be completed until it is sealed, either by
+/// [`seal`](Seal::seal) or by dropping this structure.
+#[derive(Debug)]
+pub struct Seal<T> {
+    inner: NonNull<Inner<T>>,
+}
+
+unsafe impl<T: Send> Send for Seal<T> {}
+
+impl<T> Seal<T> {
+    /// Register a new sender to the slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count of the senders exceeds `isize::MAX`.
+    pub fn fork(&self) -> Sender<T> {
+        // SAFETY: `inner` is valid since we hold a reference count of it.
+        let index = unsafe { self.inner.as_ref() }.register();
+        Sender {
+            inner: self.inner,
+            index,
+        }
+    }
+
+    /// Seal the slot, and obtain the resulting iterator if no senders exist
+    /// any longer.
+    pub fn seal(self) -> Option<SenderIter<T>> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again.
+        unsafe { Inner::release(this.inner, Relaxed) }
+    }
+}
+
+impl<T> Drop for Seal<T> {
+    fn drop(&mut self) {
+        // SAFETY: `self` is never used again.
+        drop(unsafe { Inner::release(self.inner, Relaxed) })
+    }
+}
+
+/// The resulting iterator of values that all the senders have placed into an
+/// open-ended slot, in the order of their registration.
+#[derive(Debug)]
+pub struct SenderIter<T> {
+    inner: NonNull<Inner<T>>,
+    index: usize,
+}
+
+// SAFETY: We now owns `inner`.
+unsafe impl<T: Send> Send for SenderIter<T> {}
+
+impl<T> SenderIter<T> {
+    fn len(&self) -> usize {
+        // SAFETY: We now owns `inner`.
+        unsafe { self.inner.as_ref() }.len.load(Relaxed)
+    }
+}
+
+impl<T> Iterator for SenderIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: We now owns `inner`.
+        let inner = unsafe { self.inner.as_ref() };
+
+        while self.index < self.len() {
+            // SAFETY: `index` is registered since it is less than the length.
+            let elem = unsafe { inner.element(self.index) };
+            self.index += 1;
+
+            // SAFETY: We now owns `inner`, so no atomic ordering is needed; each element is
+            // only taken once since `index` is incremented at every yield.
+            if let Some(data) = unsafe { elem.take() } {
+                return Some(data);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.len() - self.index))
+    }
+}
+
+impl<T> FusedIterator for SenderIter<T> {}
+
+impl<T> Drop for SenderIter<T> {
+    fn drop(&mut self) {
+        // If dropping a value panics, the guard drops the remaining values and
+        // deallocates the slot during unwinding.
+        struct Guard<'a, T>(&'a mut SenderIter<T>);
+
+        impl<T> Drop for Guard<'_, T> {
+            fn drop(&mut self) {
+                self.0.for_each(drop);
+                // SAFETY: We now owns `inner`, and all the values are taken above.
+                unsafe { Inner::drop_in_place(self.0.inner) }
+            }
+        }
+
+        let guard = Guard(self);
+        guard.0.for_each(drop);
+    }
+}
+
+/// Create an open-ended slot, and return its first sender and the seal.
+///
+/// New senders can be registered at any time by [`Sender::fork`] or
+/// [`Seal::fork`], and the storage grows on demand. The slot completes when
+/// it is sealed and all the senders are consumed or dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// let (s1, seal) = either_slot::array::open();
+/// let s2 = s1.fork();
+/// let s3 = seal.fork();
+/// assert!(seal.seal().is_none());
+///
+/// s3.send(3).unwrap();
+/// drop(s1);
+/// let iter = s2.send(2).unwrap_err();
+/// assert_eq!(iter.collect::<Vec<_>>(), [2, 3]);
+/// ```
+pub fn open<T>() -> (Sender<T>, Seal<T>) {
+    // One for the seal, and the other for the sender registered below.
+    let inner = Inner::new(1);
+    let seal = Seal { inner };
+    (seal.fork(), seal)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    #[cfg(not(loom))]
+    use std::thread;
+
+    #[cfg(loom)]
+    use loom::thread;
+
+    use super::open;
+
+    #[test]
+    fn send() {
+        fn inner() {
+            let (s1, seal) = open();
+            let j = thread::spawn(move || {
+                let s2 = s1.fork();
+                s1.send(1).and(s2.send(2))
+            });
+
+            let s3 = seal.fork();
+            let res = s3.send(3).and(j.join().unwrap());
+            let res = match seal.seal() {
+                Some(iter) => Err(iter),
+                None => res,
+            };
+            let mut values = res.unwrap_err().collect::<Vec<_>>();
+            values.sort();
+            assert_eq!(values, [1, 2, 3]);
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn grow() {
+        let (s, seal) = open();
+        let senders = (0..100).map(|_| s.fork()).collect::<Vec<_>>();
+        drop((s, seal));
+
+        let res = senders
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| s.send(i))
+            .fold(Ok(()), Result::and);
+        assert!(res.unwrap_err().eq(0..100));
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn drop_panic() {
+        use std::{
+            panic::{self, AssertUnwindSafe},
+            sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        };
+
+        use crate::bomb::Bomb;
+
+        let dropped = AtomicUsize::new(0);
+        let (s, seal) = open();
+        // Spread the values over multiple segments, all of which must be freed.
+        let senders = (0..100).map(|_| s.fork()).collect::<Vec<_>>();
+        drop((s, seal));
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let res = senders
+                .into_iter()
+                .enumerate()
+                .map(|(i, s)| s.send(Bomb(i == 0, &dropped)))
+                .fold(Ok(()), Result::and);
+            drop(res)
+        }));
+        assert!(res.is_err());
+        assert_eq!(dropped.load(Relaxed), 100);
+    }
+}
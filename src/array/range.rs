@@ -0,0 +1,321 @@
+//! Range senders, each of which owns a contiguous range of elements in an
+//! array slot.
+
+use core::{iter::FusedIterator, ops::Range};
+
+use super::{Header, InitIter, Inner, Place, SenderIter};
+use crate::include::*;
+
+/// The placer of a contiguous range of elements in an array slot.
+///
+/// Placing values by a range sender only decreases the reference count of the
+/// slot once, no matter how many elements it owns.
+///
+/// The user can only access the slot once by this structure.
+#[derive(Debug)]
+pub struct RangeSender<T, P: Place<T>, A: Allocator = DefaultAlloc> {
+    inner: NonNull<Inner<T, P, A>>,
+    range: Range<usize>,
+}
+
+// SAFETY: The same as `Sender`.
+unsafe impl<T: Send, P: Place<T>, A: Allocator + Send> Send for RangeSender<T, P, A> {}
+
+impl<T, P: Place<T>, A: Allocator> RangeSender<T, P, A> {
+    /// The range of indices of the elements this sender owns.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// The count of the elements this sender owns.
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Check if this sender owns no elements.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// Split the sender into 2 at the `mid` of its range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > self.len()`.
+    pub fn split_at(self, mid: usize) -> (Self, Self) {
+        assert!(mid <= self.len(), "mid > len");
+        let this = ManuallyDrop::new(self);
+        let mid = this.range.start + mid;
+        (
+            RangeSender {
+                inner: this.inner,
+                range: this.range.start..mid,
+            },
+            RangeSender {
+                inner: this.inner,
+                range: mid..this.range.end,
+            },
+        )
+    }
+
+    /// Place the values from `iter` into the elements in order, or obtain the
+    /// resulting iterator if no other senders exist any longer.
+    ///
+    /// If `iter` yields less values than the count of the elements, the
+    /// remaining elements are left empty; if it yields more, the surplus ones
+    /// are not consumed.
+    pub fn send_iter(self, iter: impl IntoIterator<Item = T>) -> Result<(), SenderIter<T, P, A>> {
+        if self.is_empty() {
+            // We hold no reference count, so the slot must not be touched.
+            mem::forget(self);
+            return Ok(());
+        }
+        // SAFETY: `inner` is valid since we hold reference counts of it.
+        let inner = unsafe { self.inner.as_ref() };
+        let elements = &inner.place.as_ref()[self.range.clone()];
+
+        // If `iter` panics, the guard releases the sender along with the values placed
+        // so far.
+        let start = self.range.start;
+        let mut guard = Placing {
+            sender: Some(self),
+            placed: start..start,
+        };
+        for (elem, value) in elements.iter().zip(iter) {
+            // SAFETY: Each sender has its ownership of its `Element` storages in its
+            // `inner`, and thus the placing is safe. Besides, the appending `Release`
+            // ordering is supplied.
+            unsafe { elem.place(value) };
+            guard.placed.end += 1;
+        }
+
+        match guard.release() {
+            Some(iter) => Err(iter),
+            None => Ok(()),
+        }
+    }
+
+    /// Clone and place the values from `values` into the elements in order, or
+    /// obtain the resulting iterator if no other senders exist any longer.
+    ///
+    /// See [`RangeSender::send_iter`] for more information.
+    pub fn send_slice(self, values: &[T]) -> Result<(), SenderIter<T, P, A>>
+    where
+        T: Clone,
+    {
+        self.send_iter(values.iter().cloned())
+    }
+
+    /// Release the reference counts of the sender, taking the arrival tickets
+    /// of the values at `placed` only, if any.
+    fn release(self, placed: Range<usize>, order: atomic::Ordering) -> Option<SenderIter<T, P, A>> {
+        let this = ManuallyDrop::new(self);
+        let len = this.len();
+        if len == 0 {
+            return None;
+        }
+        // SAFETY: We hold `len` reference counts of `inner`, and own the elements at
+        // `placed`.
+        unsafe { Header::<T>::release_placed(this.inner.cast(), len, placed, order) }
+            // SAFETY: Since the reference count is now 0, we owns `inner`, so it can be
+            // handed to the iterator safely.
+            .then(|| unsafe { SenderIter::new(this.inner) })
+    }
+}
+
+impl<T, P: Place<T>, A: Allocator> Drop for RangeSender<T, P, A> {
+    fn drop(&mut self) {
+        let this = RangeSender {
+            inner: self.inner,
+            range: self.range.clone(),
+        };
+        drop(this.release(0..0, Relaxed))
+    }
+}
+
+/// The unwinding guard of [`RangeSender::send_iter`], which releases the
+/// sender along with the values placed so far.
+struct Placing<T, P: Place<T>, A: Allocator> {
+    sender: Option<RangeSender<T, P, A>>,
+    placed: Range<usize>,
+}
+
+impl<T, P: Place<T>, A: Allocator> Placing<T, P, A> {
+    fn release(&mut self) -> Option<SenderIter<T, P, A>> {
+        let sender = self.sender.take()?;
+        sender.release(self.placed.clone(), Release)
+    }
+}
+
+impl<T, P: Place<T>, A: Allocator> Drop for Placing<T, P, A> {
+    fn drop(&mut self) {
+        drop(self.release())
+    }
+}
+
+/// An iterator over [`RangeSender`]s of a fixed size, split from an
+/// [`InitIter`].
+///
+/// See [`InitIter::chunks`] for more information.
+#[derive(Debug)]
+pub struct Chunks<T, P: Place<T>, A: Allocator = DefaultAlloc> {
+    rest: Option<RangeSender<T, P, A>>,
+    size: usize,
+}
+
+unsafe impl<T: Send, P: Place<T>, A: Allocator + Send> Send for Chunks<T, P, A> {}
+
+impl<T, P: Place<T>, A: Allocator> Iterator for Chunks<T, P, A> {
+    type Item = RangeSender<T, P, A>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.take()?;
+        if rest.is_empty() {
+            drop(rest);
+            return None;
+        }
+        let mid = self.size.min(rest.len());
+        let (chunk, rest) = rest.split_at(mid);
+        self.rest = Some(rest);
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self
+            .rest
+            .as_ref()
+            .map_or(0, |rest| rest.len().div_ceil(self.size));
+        (len, Some(len))
+    }
+}
+
+impl<T, P: Place<T>, A: Allocator> ExactSizeIterator for Chunks<T, P, A> {}
+
+impl<T, P: Place<T>, A: Allocator> FusedIterator for Chunks<T, P, A> {}
+
+impl<T, P: Place<T>, A: Allocator> InitIter<T, P, A> {
+    /// Convert the senders yet to be yielded into a single [`RangeSender`].
+    pub fn into_range(self) -> RangeSender<T, P, A> {
+        // SAFETY: See contract 1 in `Sender::new`.
+        let len = unsafe { self.inner.as_ref() }.place.as_ref().len();
+        let this = ManuallyDrop::new(self);
+        RangeSender {
+            inner: this.inner,
+            range: this.index..len,
+        }
+    }
+
+    /// Split the senders yet to be yielded into 2 [`RangeSender`]s, the
+    /// first one of which owns `mid` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > self.len()`.
+    pub fn split_at(self, mid: usize) -> (RangeSender<T, P, A>, RangeSender<T, P, A>) {
+        self.into_range().split_at(mid)
+    }
+
+    /// Split the senders yet to be yielded into [`RangeSender`]s of `size`
+    /// elements, except the last one which may own less.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "alloc")] {
+    /// let mut chunks = either_slot::array::vec(5).chunks(2);
+    /// let [c1, c2, c3] = std::array::from_fn(|_| chunks.next().unwrap());
+    /// assert_eq!((c1.range(), c2.range(), c3.range()), (0..2, 2..4, 4..5));
+    ///
+    /// c1.send_slice(&[1, 2]).unwrap();
+    /// c3.send_iter([5, 6]).unwrap();
+    /// let iter = c2.send_iter([3]).unwrap_err();
+    /// assert_eq!(iter.collect::<Vec<_>>(), [1, 2, 3, 5]);
+    /// # }
+    /// ```
+    pub fn chunks(self, size: usize) -> Chunks<T, P, A> {
+        assert!(size != 0, "chunk size must be non-zero");
+        Chunks {
+            rest: Some(self.into_range()),
+            size,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+    #[cfg(not(loom))]
+    use std::thread;
+
+    #[cfg(loom)]
+    use loom::thread;
+
+    use crate::array::vec;
+
+    #[test]
+    fn send() {
+        fn inner() {
+            let (r1, r2) = vec(5).split_at(2);
+            let j = thread::spawn(move || r1.send_iter(0..2));
+
+            let res = r2.send_iter(2..4).and(j.join().unwrap());
+            assert_eq!(res.unwrap_err().collect::<Vec<_>>(), [0, 1, 2, 3]);
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[test]
+    fn drop_one() {
+        fn inner() {
+            let mut chunks = vec(5).chunks(2);
+            let [c1, c2, c3] = core::array::from_fn(|_| chunks.next().unwrap());
+            let j = thread::spawn(move || drop(c2));
+
+            let res = c1.send_slice(&[0, 1]);
+            j.join().unwrap();
+            let res = res.and(c3.send_iter([4]));
+            assert_eq!(res.unwrap_err().collect::<Vec<_>>(), [0, 1, 4]);
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn ordered() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        use crate::array::vec_ordered;
+
+        let mut chunks = vec_ordered(6).chunks(2);
+        let [c1, c2, c3] = core::array::from_fn(|_| chunks.next().unwrap());
+        c2.send_iter([2]).unwrap();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            c3.send_iter((4..).map(|i| if i < 5 { i } else { panic!() }))
+        }));
+        assert!(res.is_err());
+
+        let iter = c1.send_slice(&[0, 1]).unwrap_err();
+        let ranked = iter.ranked().collect::<Vec<_>>();
+        assert_eq!(
+            ranked,
+            [
+                (Some(2), 0, 0),
+                (Some(3), 1, 1),
+                (Some(0), 2, 2),
+                (Some(1), 4, 4)
+            ]
+        );
+    }
+}
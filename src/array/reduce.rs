@@ -0,0 +1,407 @@
+//! Reducer slots, whose senders fold their values into a single accumulator
+//! instead of storing them.
+
+use core::{
+    fmt,
+    iter::{FusedIterator, TrustedLen},
+    marker::PhantomData,
+    ptr,
+};
+
+use super::MAX_COUNT;
+use crate::{include::*, lock::SpinLock, word::Word, SlotSender};
+
+/// The accumulator of a reducer slot, into which the values of the senders are
+/// folded upon arrival.
+///
+/// The senders fold their values concurrently through a shared reference, so
+/// the accumulator must be [`Sync`] for them to be sent across threads.
+pub trait Accumulator<T> {
+    /// The folded result obtained by the last sender.
+    type Output;
+
+    /// Fold `value` into the accumulator.
+    fn fold(&self, value: T);
+
+    /// Obtain the folded result.
+    fn into_output(self) -> Self::Output;
+}
+
+/// An accumulator folded under a tiny spin lock.
+///
+/// See [`reduce`] for more information.
+#[derive(Debug)]
+pub struct Locked<R, F>(SpinLock<(R, F)>);
+
+impl<T, R, F> Accumulator<T> for Locked<R, F>
+where
+    F: FnMut(&mut R, T),
+{
+    type Output = R;
+
+    fn fold(&self, value: T) {
+        self.0.with(|(acc, op)| op(acc, value))
+    }
+
+    fn into_output(self) -> R {
+        self.0.into_inner().0
+    }
+}
+
+/// An accumulator packed into an atomic word, folded by a CAS loop without any
+/// lock.
+///
+/// See [`reduce_lock_free`] for more information.
+pub struct LockFree<R, F> {
+    acc: AtomicUsize,
+    op: F,
+    marker: PhantomData<R>,
+}
+
+impl<R: Word + fmt::Debug, F> fmt::Debug for LockFree<R, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockFree")
+            .field("acc", &R::from_bits(self.acc.load(Relaxed) as u64))
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: Word, F> LockFree<R, F> {
+    const MASK: usize = (1 << R::BITS) - 1;
+
+    fn new(init: R, op: F) -> Self {
+        LockFree {
+            acc: AtomicUsize::new(init.into_bits() as usize & Self::MASK),
+            op,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, R, F> Accumulator<T> for LockFree<R, F>
+where
+    R: Word,
+    F: Fn(R, &T) -> R,
+{
+    type Output = R;
+
+    fn fold(&self, value: T) {
+        // The ordering between folds is supplied by the reference count, so the
+        // accumulator itself needs no additional ordering.
+        let mut bits = self.acc.load(Relaxed);
+        loop {
+            let new =
+                (self.op)(R::from_bits(bits as u64), &value).into_bits() as usize & Self::MASK;
+            match self.acc.compare_exchange_weak(bits, new, Relaxed, Relaxed) {
+                Ok(_) => break,
+                Err(current) => bits = current,
+            }
+        }
+    }
+
+    fn into_output(self) -> R {
+        R::from_bits(self.acc.load(Relaxed) as u64)
+    }
+}
+
+struct Inner<T, A> {
+    count: AtomicUsize,
+    acc: A,
+    marker: PhantomData<fn(T)>,
+}
+
+impl<T, A: Accumulator<T>> Inner<T, A> {
+    const LAYOUT: Layout = Layout::new::<Self>();
+
+    fn new(count: usize, acc: A) -> NonNull<Self> {
+        assert!(
+            count <= MAX_COUNT,
+            "the length of the slot must not exceed `isize::MAX`"
+        );
+        assert!(count > 0, "the slot must not be empty");
+
+        let memory = match Global.allocate(Self::LAYOUT) {
+            Ok(memory) => memory.cast::<Self>(),
+            Err(_) => handle_alloc_error(Self::LAYOUT),
+        };
+        let value = Self {
+            count: AtomicUsize::new(count),
+            acc,
+            marker: PhantomData,
+        };
+        // SAFETY: We own this fresh uninitialized memory whose layout is the same as
+        // this type.
+        unsafe { memory.as_ptr().write(value) }
+        memory
+    }
+
+    /// # Safety
+    ///
+    /// 1. `this` must own a valid `Inner` uniquely (a.k.a. no other references
+    ///    to the structure), and use an [`Acquire`] fence if atomic ordering is
+    ///    desired.
+    /// 2. The caller must not use `this` again since it is consumed and dropped
+    ///    in this function.
+    unsafe fn into_output(this: NonNull<Self>) -> A::Output {
+        // SAFETY: See contract 1 & 2.
+        let Inner { acc, .. } = unsafe { this.as_ptr().read() };
+        // SAFETY: See contract 2.
+        unsafe { Global.deallocate(this.cast(), Self::LAYOUT) };
+
+        acc.into_output()
+    }
+
+    /// # Safety
+    ///
+    /// See [`Inner::into_output`].
+    unsafe fn drop_in_place(this: NonNull<Self>) {
+        // SAFETY: See contract 2.
+        unsafe { ptr::drop_in_place(this.as_ptr()) };
+        // SAFETY: See contract 2.
+        unsafe { Global.deallocate(this.cast(), Self::LAYOUT) };
+    }
+}
+
+/// The placer of a reducer slot.
+///
+/// The user can only access the slot once by this structure.
+#[derive(Debug)]
+pub struct Sender<T, A: Accumulator<T>> {
+    inner: NonNull<Inner<T, A>>,
+}
+
+// SAFETY: The value is moved into the accumulator, which is shared by all the
+// senders and folded through a shared reference, so it must be `Sync` besides
+// `Send`.
+unsafe impl<T: Send, A: Accumulator<T> + Send + Sync> Send for Sender<T, A> {}
+
+impl<T, A: Accumulator<T>> Sender<T, A> {
+    /// Fold the value into the accumulator of the slot, or obtain the folded
+    /// result if no other senders exist any longer.
+    ///
+    /// If the operation panics, the value is lost and this sender is
+    /// considered dropped.
+    pub fn send(self, value: T) -> Result<(), A::Output> {
+        // SAFETY: `inner` is immutable until the reference count drops to 0.
+        let inner = unsafe { self.inner.as_ref() };
+
+        inner.acc.fold(value);
+        let fetch_sub = inner.count.fetch_sub(1, Release);
+
+        let pointer = self.inner;
+        // We don't want to call the dropper anymore because it decreases the reference
+        // count once more.
+        mem::forget(self);
+
+        if fetch_sub == 1 {
+            // SAFETY: We use `Acquire` fence here to observe other executions of folding
+            // values. And since the reference count is now 0, we owns `inner`, so the
+            // accumulator can be taken out safely.
+            atomic::fence(Acquire);
+            return Err(unsafe { Inner::into_output(pointer) });
+        }
+        Ok(())
+    }
+}
+
+impl<T, A: Accumulator<T>> Drop for Sender<T, A> {
+    fn drop(&mut self) {
+        // SAFETY: `inner` is immutable until the reference count drops to 0.
+        let inner = unsafe { self.inner.as_ref() };
+        // No additional ordering is used because we now have no more
+        // observations/modifications to the accumulator, except...
+        if inner.count.fetch_sub(1, Relaxed) == 1 {
+            // SAFETY: ... we now owns our `inner`.
+            atomic::fence(Acquire);
+            unsafe { Inner::drop_in_place(self.inner) }
+        }
+    }
+}
+
+impl<T, A: Accumulator<T>> SlotSender for Sender<T, A> {
+    type Item = T;
+    type Completion = A::Output;
+
+    fn send(self, item: T) -> Result<(), A::Output> {
+        Sender::send(self, item)
+    }
+}
+
+/// The initialization iterator for senders of a reducer slot.
+///
+/// See [`InitIter`](super::InitIter) for more information.
+#[derive(Debug)]
+pub struct InitIter<T, A: Accumulator<T>> {
+    inner: NonNull<Inner<T, A>>,
+    rest: usize,
+}
+
+unsafe impl<T: Send, A: Accumulator<T> + Send + Sync> Send for InitIter<T, A> {}
+
+impl<T, A: Accumulator<T>> Iterator for InitIter<T, A> {
+    type Item = Sender<T, A>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rest = self.rest.checked_sub(1)?;
+        Some(Sender { inner: self.inner })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.rest, Some(self.rest))
+    }
+}
+
+impl<T, A: Accumulator<T>> Drop for InitIter<T, A> {
+    fn drop(&mut self) {
+        self.for_each(drop)
+    }
+}
+
+impl<T, A: Accumulator<T>> ExactSizeIterator for InitIter<T, A> {}
+
+impl<T, A: Accumulator<T>> FusedIterator for InitIter<T, A> {}
+
+unsafe impl<T, A: Accumulator<T>> TrustedLen for InitIter<T, A> {}
+
+/// Construct an iterator of `count` senders to a reducer slot, whose values
+/// will be folded into the accumulator `acc` upon arrival.
+///
+/// See [`reduce`] and [`reduce_lock_free`] for the accumulators provided by
+/// this crate.
+pub fn from_accumulator<T, A: Accumulator<T>>(count: usize, acc: A) -> InitIter<T, A> {
+    let inner = Inner::new(count, acc);
+    InitIter { inner, rest: count }
+}
+
+/// Construct an iterator of `count` senders to a reducer slot, whose values
+/// will be folded into `init` by `op` upon arrival.
+///
+/// Unlike the other array slots, the values are not stored at all, and the last
+/// sender obtains the folded result instead of a [`SenderIter`]. Folding is
+/// serialized by a tiny spin lock in the slot, so `op` should be cheap. For
+/// small [`Copy`] accumulators, see [`reduce_lock_free`].
+///
+/// [`SenderIter`]: super::SenderIter
+///
+/// # Examples
+///
+/// ```rust
+/// let mut senders = either_slot::array::reduce(3, 0, |acc, x| *acc += x);
+/// let [s1, s2, s3] = std::array::from_fn(|_| senders.next().unwrap());
+/// s1.send(1).unwrap();
+/// drop(s2);
+/// assert_eq!(s3.send(3), Err(4));
+/// ```
+pub fn reduce<T, R, F>(count: usize, init: R, op: F) -> InitIter<T, Locked<R, F>>
+where
+    F: FnMut(&mut R, T),
+{
+    from_accumulator(count, Locked(SpinLock::new((init, op))))
+}
+
+/// Construct an iterator of `count` senders to a reducer slot, whose values
+/// will be folded into `init` by `op` upon arrival without any lock.
+///
+/// The accumulator is packed into an atomic word as a [`Word`], and each value
+/// is folded by a CAS loop, so `op` may be called more than once for the same
+/// value under contention, and must be free of side effects.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut senders = either_slot::array::reduce_lock_free(3, 0u32, |acc, x: &u32| acc.max(*x));
+/// let [s1, s2, s3] = std::array::from_fn(|_| senders.next().unwrap());
+/// s1.send(5).unwrap();
+/// drop(s2);
+/// assert_eq!(s3.send(3), Err(5));
+/// ```
+pub fn reduce_lock_free<T, R, F>(count: usize, init: R, op: F) -> InitIter<T, LockFree<R, F>>
+where
+    R: Word,
+    F: Fn(R, &T) -> R,
+{
+    const { assert!(R::BITS < usize::BITS, "`R` does not fit in the word") }
+    from_accumulator(count, LockFree::new(init, op))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    #[cfg(not(loom))]
+    use std::thread;
+
+    #[cfg(loom)]
+    use loom::thread;
+
+    use super::{reduce, reduce_lock_free};
+
+    #[test]
+    fn send() {
+        fn inner() {
+            let mut senders = reduce(2, 0, |acc, x| *acc += x);
+            let (s1, s2) = (senders.next().unwrap(), senders.next().unwrap());
+            let j = thread::spawn(move || s1.send(1));
+
+            let res = s2.send(2).and(j.join().unwrap());
+            assert_eq!(res, Err(3));
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[test]
+    fn lock_free() {
+        fn inner() {
+            let mut senders = reduce_lock_free(3, 0u32, |acc, x: &u32| acc + x);
+            let [s1, s2, s3] = core::array::from_fn(|_| senders.next().unwrap());
+            let j1 = thread::spawn(move || s1.send(1));
+            let j2 = thread::spawn(move || s2.send(2));
+
+            drop(s3);
+            let res = j1.join().unwrap().and(j2.join().unwrap());
+            assert!(matches!(res, Ok(()) | Err(3)));
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[test]
+    fn drop_one() {
+        fn inner() {
+            let j = reduce(3, Vec::new(), Vec::push)
+                .enumerate()
+                .map(|(i, s)| {
+                    if i != 1 {
+                        thread::spawn(move || s.send(i))
+                    } else {
+                        thread::spawn(move || {
+                            drop(s);
+                            Ok(())
+                        })
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let res = j
+                .into_iter()
+                .map(|j| j.join().unwrap())
+                .fold(Ok(()), Result::and);
+
+            if let Err(mut acc) = res {
+                acc.sort();
+                assert_eq!(acc, [0, 2]);
+            }
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+}
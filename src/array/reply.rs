@@ -0,0 +1,433 @@
+//! Array slots whose senders get a reply back from the last sender, forming a
+//! gather-compute-scatter round.
+
+use alloc::vec::Vec;
+use core::{
+    future::Future,
+    iter::{self, FusedIterator, TrustedLen},
+    pin::Pin,
+    ptr,
+    task::{Context, Poll, Waker},
+};
+
+use super::{from_place, Element, Place, SenderIter};
+use crate::{include::*, lock::SpinLock};
+
+/// The error returned when a reply can never be delivered, because the last
+/// sender dropped its [`Responder`] without responding, or dropped itself
+/// instead of sending its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Canceled;
+
+#[derive(Debug)]
+struct State<R> {
+    value: Option<R>,
+    waker: Option<Waker>,
+    /// Whether the value is delivered or canceled by the responder.
+    responded: bool,
+    /// Whether the receiving half is gone.
+    closed: bool,
+}
+
+struct Replies<R> {
+    refs: AtomicUsize,
+    states: Vec<SpinLock<State<R>>>,
+}
+
+impl<R> Replies<R> {
+    const LAYOUT: Layout = Layout::new::<Self>();
+
+    fn new(count: usize) -> NonNull<Self> {
+        let memory = match Global.allocate(Self::LAYOUT) {
+            Ok(memory) => memory.cast::<Self>(),
+            Err(_) => handle_alloc_error(Self::LAYOUT),
+        };
+        let state = || {
+            SpinLock::new(State {
+                value: None,
+                waker: None,
+                responded: false,
+                closed: false,
+            })
+        };
+        let value = Self {
+            refs: AtomicUsize::new(count),
+            states: iter::repeat_with(state).take(count).collect(),
+        };
+        // SAFETY: We own this fresh uninitialized memory whose layout is the same as
+        // this type.
+        unsafe { memory.as_ptr().write(value) }
+        memory
+    }
+
+    fn respond(&self, index: usize, value: R) -> Result<(), R> {
+        let waker = self.states[index].with(|state| {
+            if state.responded || state.closed {
+                return Err(value);
+            }
+            state.responded = true;
+            state.value = Some(value);
+            Ok(state.waker.take())
+        })?;
+        waker.into_iter().for_each(Waker::wake);
+        Ok(())
+    }
+
+    fn cancel(&self, index: usize) {
+        let waker = self.states[index].with(|state| {
+            let responded = mem::replace(&mut state.responded, true);
+            (!responded).then(|| state.waker.take()).flatten()
+        });
+        waker.into_iter().for_each(Waker::wake);
+    }
+
+    fn close(&self, index: usize) {
+        let value = self.states[index].with(|state| {
+            state.closed = true;
+            state.value.take()
+        });
+        drop(value);
+    }
+
+    /// # Safety
+    ///
+    /// 1. `this` must hold a valid reference of `Replies`.
+    /// 2. The caller must not use `this` again since the reference is consumed
+    ///    in this function.
+    unsafe fn release(this: NonNull<Self>) {
+        // SAFETY: See contract 1.
+        let replies = unsafe { this.as_ref() };
+        if replies.refs.fetch_sub(1, Release) == 1 {
+            atomic::fence(Acquire);
+            // SAFETY: We now owns `this`; see contract 2.
+            unsafe { ptr::drop_in_place(this.as_ptr()) };
+            unsafe { Global.deallocate(this.cast(), Self::LAYOUT) };
+        }
+    }
+}
+
+/// The resulting iterator and the responder obtained by the last sender of a
+/// reply slot.
+pub type Finish<T, R, P> = (SenderIter<T, P>, Responder<R>);
+
+/// The placer of a reply slot.
+///
+/// The user can only access the slot once by this structure.
+///
+/// This sender doesn't implement [`SlotSender`](crate::SlotSender), since a
+/// successful send yields a [`Reply`] handle, which doesn't fit in the
+/// `Result<(), _>` returned by the trait.
+#[derive(Debug)]
+pub struct Sender<T, R, P: Place<T>> {
+    sender: ManuallyDrop<super::Sender<T, P>>,
+    replies: NonNull<Replies<R>>,
+}
+
+// SAFETY: Replies are sent from the last sender to the others, so `R` must be
+// `Send` as well.
+unsafe impl<T: Send, R: Send, P: Place<T>> Send for Sender<T, R, P> {}
+
+impl<T, R, P: Place<T>> Sender<T, R, P> {
+    /// Place the value into the slot and obtain the handle to the reply of
+    /// this sender, or obtain the resulting iterator and the responder if no
+    /// other senders exist any longer.
+    ///
+    /// The last sender gets no reply handle of its own; anything responded to
+    /// its index is discarded.
+    pub fn send(self, value: T) -> Result<Reply<R>, Finish<T, R, P>> {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again, so does the sender.
+        let sender = unsafe { ManuallyDrop::take(&mut this.sender) };
+        let (replies, index) = (this.replies, sender.index);
+
+        match sender.send(value) {
+            Ok(()) => Ok(Reply { replies, index }),
+            Err(iter) => {
+                // SAFETY: `replies` is valid since we hold a reference of it.
+                unsafe { replies.as_ref() }.close(index);
+                Err((iter, Responder { replies }))
+            }
+        }
+    }
+}
+
+impl<T, R, P: Place<T>> Drop for Sender<T, R, P> {
+    fn drop(&mut self) {
+        // SAFETY: `self.sender` is never used again.
+        let sender = unsafe { ManuallyDrop::take(&mut self.sender) };
+        // SAFETY: `replies` is valid since we hold a reference of it.
+        unsafe { self.replies.as_ref() }.close(sender.index);
+
+        match sender.abandon() {
+            // No responder will ever exist, so we hand our reference to a temporary one,
+            // which cancels all the replies.
+            Some(iter) => {
+                drop(iter);
+                drop(Responder {
+                    replies: self.replies,
+                })
+            }
+            // SAFETY: We hold a reference of `replies`.
+            None => unsafe { Replies::release(self.replies) },
+        }
+    }
+}
+
+/// The handle to the reply of a sender.
+///
+/// The reply can be obtained either by blocking with [`Reply::wait`], or by
+/// awaiting the handle.
+#[derive(Debug)]
+pub struct Reply<R> {
+    replies: NonNull<Replies<R>>,
+    index: usize,
+}
+
+// SAFETY: The reply is sent from the last sender.
+unsafe impl<R: Send> Send for Reply<R> {}
+
+impl<R> Reply<R> {
+    /// The index of the sender who owned this reply handle.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    fn poll_reply(&self, waker: Option<&Waker>) -> Poll<Result<R, Canceled>> {
+        // SAFETY: `replies` is valid since we hold a reference of it.
+        let replies = unsafe { self.replies.as_ref() };
+        replies.states[self.index].with(|state| match state.value.take() {
+            Some(value) => Poll::Ready(Ok(value)),
+            None if state.responded => Poll::Ready(Err(Canceled)),
+            None => {
+                if let Some(waker) = waker {
+                    state.waker = Some(waker.clone());
+                }
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Attempt to obtain the reply without blocking.
+    pub fn try_recv(&mut self) -> Poll<Result<R, Canceled>> {
+        self.poll_reply(None)
+    }
+
+    /// Spin until the reply is obtained.
+    ///
+    /// Since this crate is `no_std`, this function cannot park the thread.
+    /// Awaiting the handle in an asynchronous context is recommended for
+    /// long computations.
+    pub fn wait(self) -> Result<R, Canceled> {
+        loop {
+            match self.poll_reply(None) {
+                Poll::Ready(res) => break res,
+                Poll::Pending => hint::spin_loop(),
+            }
+        }
+    }
+}
+
+impl<R> Future for Reply<R> {
+    type Output = Result<R, Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_reply(Some(cx.waker()))
+    }
+}
+
+impl<R> Drop for Reply<R> {
+    fn drop(&mut self) {
+        // SAFETY: `replies` is valid since we hold a reference of it.
+        unsafe { self.replies.as_ref() }.close(self.index);
+        // SAFETY: We hold a reference of `replies`.
+        unsafe { Replies::release(self.replies) }
+    }
+}
+
+/// The responder delivering replies to each sender of a reply slot.
+///
+/// When dropped, all the senders yet to be responded get [`Canceled`].
+#[derive(Debug)]
+pub struct Responder<R> {
+    replies: NonNull<Replies<R>>,
+}
+
+// SAFETY: The replies are sent to the other senders.
+unsafe impl<R: Send> Send for Responder<R> {}
+
+impl<R> Responder<R> {
+    /// The count of the senders of the slot, including the dropped ones.
+    pub fn count(&self) -> usize {
+        // SAFETY: `replies` is valid since we hold a reference of it.
+        unsafe { self.replies.as_ref() }.states.len()
+    }
+
+    /// Deliver the reply to the sender of `index`.
+    ///
+    /// Returns the value back if the sender has been dropped or has been
+    /// responded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn respond(&self, index: usize, value: R) -> Result<(), R> {
+        // SAFETY: `replies` is valid since we hold a reference of it.
+        unsafe { self.replies.as_ref() }.respond(index, value)
+    }
+}
+
+impl<R> Drop for Responder<R> {
+    fn drop(&mut self) {
+        // SAFETY: `replies` is valid since we hold a reference of it.
+        let replies = unsafe { self.replies.as_ref() };
+        (0..replies.states.len()).for_each(|index| replies.cancel(index));
+        // SAFETY: We hold a reference of `replies`.
+        unsafe { Replies::release(self.replies) }
+    }
+}
+
+/// The initialization iterator for senders of a reply slot.
+///
+/// See [`InitIter`](super::InitIter) for more information.
+#[derive(Debug)]
+pub struct InitIter<T, R, P: Place<T>> {
+    iter: super::InitIter<T, P>,
+    replies: NonNull<Replies<R>>,
+}
+
+unsafe impl<T: Send, R: Send, P: Place<T>> Send for InitIter<T, R, P> {}
+
+impl<T, R, P: Place<T>> Iterator for InitIter<T, R, P> {
+    type Item = Sender<T, R, P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sender = self.iter.next()?;
+        Some(Sender {
+            sender: ManuallyDrop::new(sender),
+            replies: self.replies,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, R, P: Place<T>> Drop for InitIter<T, R, P> {
+    fn drop(&mut self) {
+        self.for_each(drop)
+    }
+}
+
+impl<T, R, P: Place<T>> ExactSizeIterator for InitIter<T, R, P> {}
+
+impl<T, R, P: Place<T>> FusedIterator for InitIter<T, R, P> {}
+
+unsafe impl<T, R, P: Place<T>> TrustedLen for InitIter<T, R, P> {}
+
+/// Construct an iterator of senders to a reply slot, whose values will be
+/// placed on `place`.
+///
+/// Every sender but the last one gets a [`Reply`] handle from
+/// [`send`](Sender::send), while the last one gets the resulting
+/// [`SenderIter`] and a [`Responder`] to deliver the replies to each index.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut senders = either_slot::array::vec_reply(3);
+/// let [s1, s2, s3] = std::array::from_fn(|_| senders.next().unwrap());
+///
+/// let r1 = s1.send(1).unwrap();
+/// let r2 = s2.send(2).unwrap();
+/// let (iter, responder) = s3.send(3).unwrap_err();
+///
+/// let sum: i32 = iter.sum();
+/// for index in 0..responder.count() {
+///     let _ = responder.respond(index, sum * index as i32);
+/// }
+/// assert_eq!(r1.wait(), Ok(0));
+/// assert_eq!(r2.wait(), Ok(6));
+/// ```
+pub fn from_place_reply<T, R, P: Place<T>>(place: P) -> InitIter<T, R, P> {
+    let iter = from_place(place);
+    let replies = Replies::new(iter.len());
+    InitIter { iter, replies }
+}
+
+/// Construct an iterator of senders to a reply slot, whose values will be
+/// placed on a [`Vec`].
+///
+/// See [`from_place_reply`] for more information.
+pub fn vec_reply<T, R>(count: usize) -> InitIter<T, R, Vec<Element<T>>> {
+    from_place_reply(Element::vec(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    #[cfg(not(loom))]
+    use std::thread;
+
+    #[cfg(loom)]
+    use loom::thread;
+
+    use super::{vec_reply, Canceled, Finish, Reply};
+    use crate::array::Element;
+
+    type SendResult = Result<Reply<i32>, Finish<i32, i32, Vec<Element<i32>>>>;
+
+    fn respond_sum(res: SendResult) -> Option<Reply<i32>> {
+        match res {
+            Ok(reply) => Some(reply),
+            Err((iter, responder)) => {
+                let sum = iter.sum::<i32>();
+                (0..responder.count()).for_each(|i| {
+                    let _ = responder.respond(i, sum);
+                });
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn send() {
+        fn inner() {
+            let mut senders = vec_reply(2);
+            let (s1, s2) = (senders.next().unwrap(), senders.next().unwrap());
+            let j = thread::spawn(move || s1.send(1));
+
+            let r2 = respond_sum(s2.send(1));
+            let r1 = respond_sum(j.join().unwrap());
+
+            let reply = r1.xor(r2).unwrap();
+            assert_eq!(reply.wait(), Ok(2));
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[test]
+    fn drop_last() {
+        fn inner() {
+            let mut senders = vec_reply::<i32, ()>(2);
+            let (s1, s2) = (senders.next().unwrap(), senders.next().unwrap());
+            let j = thread::spawn(move || drop(s2));
+
+            let res = s1.send(1);
+            j.join().unwrap();
+            match res {
+                Ok(reply) => assert_eq!(reply.wait(), Err(Canceled)),
+                Err((iter, _)) => assert_eq!(iter.collect::<Vec<_>>(), [1]),
+            }
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+}
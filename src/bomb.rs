@@ -0,0 +1,14 @@
+//! The test fixture of values panicking on drop.
+
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+/// A value counting its drops in `.1`, which panics on drop if `.0` is set.
+#[derive(Debug)]
+pub(crate) struct Bomb<'a>(pub bool, pub &'a AtomicUsize);
+
+impl Drop for Bomb<'_> {
+    fn drop(&mut self) {
+        self.1.fetch_add(1, Relaxed);
+        assert!(!self.0, "boom");
+    }
+}
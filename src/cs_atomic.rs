@@ -0,0 +1,140 @@
+//! Atomics emulated by critical sections, for targets without native atomic
+//! CAS or RMW operations, e.g. thumbv6m.
+//!
+//! Every operation runs in a [`critical_section::with`] block, which supplies
+//! all the orderings needed, so the orderings passed in are ignored. The
+//! critical section implementation is provided by the final binary, e.g. by
+//! the HAL on bare-metal targets, or by the `std` feature of the
+//! `critical-section` crate on hosted ones.
+
+// Not every operation is used on every atomic type.
+#![allow(dead_code)]
+
+use core::{cell::UnsafeCell, fmt};
+
+pub use self::atomic::Ordering;
+
+pub mod atomic {
+    pub use core::sync::atomic::Ordering;
+
+    /// An atomic fence, which only needs to restrain the compiler since every
+    /// atomic operation is already synchronized by a critical section.
+    pub fn fence(order: Ordering) {
+        core::sync::atomic::compiler_fence(order)
+    }
+}
+
+macro_rules! atomic_common {
+    ($atomic:ident$(<$param:ident>)?, $t:ty) => {
+        // SAFETY: The value is only accessed in critical sections.
+        unsafe impl$(<$param>)? Sync for $atomic$(<$param>)? {}
+
+        impl$(<$param>)? $atomic$(<$param>)? {
+            pub const fn new(value: $t) -> Self {
+                $atomic(UnsafeCell::new(value))
+            }
+
+            fn with<R>(&self, f: impl FnOnce(&mut $t) -> R) -> R {
+                critical_section::with(|_| {
+                    // SAFETY: The critical section grants us exclusive access to the value.
+                    f(unsafe { &mut *self.0.get() })
+                })
+            }
+
+            pub fn load(&self, _: Ordering) -> $t {
+                self.with(|value| *value)
+            }
+
+            pub fn store(&self, new: $t, _: Ordering) {
+                self.with(|value| *value = new)
+            }
+
+            pub fn swap(&self, new: $t, _: Ordering) -> $t {
+                self.with(|value| core::mem::replace(value, new))
+            }
+
+            pub fn compare_exchange(
+                &self,
+                current: $t,
+                new: $t,
+                _: Ordering,
+                _: Ordering,
+            ) -> Result<$t, $t> {
+                self.with(|value| {
+                    let old = *value;
+                    if old == current {
+                        *value = new;
+                        Ok(old)
+                    } else {
+                        Err(old)
+                    }
+                })
+            }
+
+            pub fn compare_exchange_weak(
+                &self,
+                current: $t,
+                new: $t,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$t, $t> {
+                self.compare_exchange(current, new, success, failure)
+            }
+        }
+
+        impl$(<$param>)? fmt::Debug for $atomic$(<$param>)? {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+            }
+        }
+    };
+}
+
+macro_rules! atomic_int {
+    ($($atomic:ident: $t:ty),* $(,)?) => {$(
+        /// An integer type which can be safely shared between threads.
+        #[repr(transparent)]
+        pub struct $atomic(UnsafeCell<$t>);
+
+        atomic_common!($atomic, $t);
+
+        impl $atomic {
+            pub fn fetch_add(&self, val: $t, _: Ordering) -> $t {
+                self.with(|value| core::mem::replace(value, value.wrapping_add(val)))
+            }
+
+            pub fn fetch_sub(&self, val: $t, _: Ordering) -> $t {
+                self.with(|value| core::mem::replace(value, value.wrapping_sub(val)))
+            }
+
+            pub fn fetch_or(&self, val: $t, _: Ordering) -> $t {
+                self.with(|value| core::mem::replace(value, *value | val))
+            }
+        }
+
+        impl Default for $atomic {
+            fn default() -> Self {
+                Self::new(0)
+            }
+        }
+    )*};
+}
+atomic_int!(AtomicU8: u8, AtomicU32: u32, AtomicU64: u64, AtomicUsize: usize);
+
+/// A boolean type which can be safely shared between threads.
+#[repr(transparent)]
+pub struct AtomicBool(UnsafeCell<bool>);
+atomic_common!(AtomicBool, bool);
+
+impl Default for AtomicBool {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+/// A raw pointer type which can be safely shared between threads.
+#[repr(transparent)]
+pub struct AtomicPtr<T>(UnsafeCell<*mut T>);
+// SAFETY: The same as `core::sync::atomic::AtomicPtr`.
+unsafe impl<T> Send for AtomicPtr<T> {}
+atomic_common!(AtomicPtr<T>, *mut T);
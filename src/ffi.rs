@@ -0,0 +1,394 @@
+//! C FFI layer over either and array slots.
+//!
+//! The functions in this module are exported with the `es_` prefix over opaque
+//! handles, and the corresponding header is `include/either_slot.h`. Payloads
+//! are `void*` pointers carried along with their destructors, which are called
+//! if the payloads are discarded by the slot.
+//!
+//! Every handle returned to the caller must be consumed exactly once, either by
+//! sending through it or by its `drop` / `free` function.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{ffi::c_void, ptr};
+
+use crate::{
+    array::{self, Element},
+    ASender, BSender, SendError,
+};
+
+/// A type-erased payload passed through the slots.
+///
+/// The payload must be safe to send across threads: it may be received, or
+/// destroyed by `drop`, on whichever thread completes the slot.
+#[repr(C)]
+#[derive(Debug)]
+pub struct EsPayload {
+    /// The pointer to the payload data.
+    pub data: *mut c_void,
+    /// The destructor of `data`, called if the payload is discarded by the
+    /// slot. May be null.
+    pub drop: Option<unsafe extern "C" fn(*mut c_void)>,
+}
+
+/// The owned payload inside the slots, which calls its destructor on drop.
+struct Payload(EsPayload);
+
+// SAFETY: The caller guarantees the payloads can be sent between threads.
+unsafe impl Send for Payload {}
+
+impl Payload {
+    fn into_raw(self) -> EsPayload {
+        let payload = EsPayload {
+            data: self.0.data,
+            drop: self.0.drop,
+        };
+        core::mem::forget(self);
+        payload
+    }
+}
+
+impl Drop for Payload {
+    fn drop(&mut self) {
+        if let Some(drop) = self.0.drop {
+            // SAFETY: The caller guarantees the destructor is valid for `data`.
+            unsafe { drop(self.0.data) }
+        }
+    }
+}
+
+/// The result status of sending through a slot.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EsStatus {
+    /// The payload is placed into the slot.
+    Sent = 0,
+    /// The slot is completed, and the result is written to the output.
+    Received = 1,
+    /// The other sender is dropped, and the payload is given back untouched.
+    Disconnected = 2,
+}
+
+/// The opaque handle of the sender of `A` of an either slot.
+pub struct EsASender {
+    _private: [u8; 0],
+}
+
+/// The opaque handle of the sender of `B` of an either slot.
+pub struct EsBSender {
+    _private: [u8; 0],
+}
+
+/// The opaque handle of the senders of an array slot not taken yet.
+pub struct EsArray {
+    _private: [u8; 0],
+}
+
+/// The opaque handle of a sender of an array slot.
+pub struct EsArraySender {
+    _private: [u8; 0],
+}
+
+/// The opaque handle of the resulting iterator of an array slot.
+pub struct EsIter {
+    _private: [u8; 0],
+}
+
+type ArraySender = array::Sender<Payload, Vec<Element<Payload>>>;
+type Iter = array::SenderIter<Payload, Vec<Element<Payload>>>;
+
+/// Create an either slot, and write its senders to `a` and `b`.
+///
+/// # Safety
+///
+/// `a` and `b` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn es_either_new(a: *mut *mut EsASender, b: *mut *mut EsBSender) {
+    let (sa, sb) = crate::either::<Payload, Payload>();
+    unsafe {
+        a.write(sa.into_raw().cast());
+        b.write(sb.into_raw().cast());
+    }
+}
+
+/// Send `payload` through the sender of `A`, consuming the sender.
+///
+/// - On [`EsStatus::Sent`], the slot takes the ownership of `payload`.
+/// - On [`EsStatus::Received`], the payload of the other sender is written to
+///   `out`, whose ownership is transferred to the caller, and `payload` is left
+///   to the caller as well.
+/// - On [`EsStatus::Disconnected`], `payload` is left to the caller.
+///
+/// # Safety
+///
+/// - `sender` must be obtained from [`es_either_new`] and not consumed yet.
+/// - `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn es_either_send_a(
+    sender: *mut EsASender,
+    payload: EsPayload,
+    out: *mut EsPayload,
+) -> EsStatus {
+    let sender = unsafe { ASender::<Payload, Payload>::from_raw(sender.cast()) };
+    unsafe { status(sender.send(Payload(payload)), out) }
+}
+
+/// Send `payload` through the sender of `B`, consuming the sender.
+///
+/// See [`es_either_send_a`] for more information.
+///
+/// # Safety
+///
+/// See [`es_either_send_a`].
+#[no_mangle]
+pub unsafe extern "C" fn es_either_send_b(
+    sender: *mut EsBSender,
+    payload: EsPayload,
+    out: *mut EsPayload,
+) -> EsStatus {
+    let sender = unsafe { BSender::<Payload, Payload>::from_raw(sender.cast()) };
+    unsafe { status(sender.send(Payload(payload)), out) }
+}
+
+/// # Safety
+///
+/// `out` must be valid for writes.
+unsafe fn status(res: Result<(), SendError<Payload, Payload>>, out: *mut EsPayload) -> EsStatus {
+    match res {
+        Ok(()) => EsStatus::Sent,
+        Err(SendError::Received(this, other)) => {
+            this.into_raw();
+            unsafe { out.write(other.into_raw()) };
+            EsStatus::Received
+        }
+        Err(SendError::Disconnected(this)) => {
+            this.into_raw();
+            EsStatus::Disconnected
+        }
+    }
+}
+
+/// Drop the sender of `A`, destroying the payload of the other sender if it
+/// has been sent.
+///
+/// # Safety
+///
+/// `sender` must be obtained from [`es_either_new`] and not consumed yet.
+#[no_mangle]
+pub unsafe extern "C" fn es_either_drop_a(sender: *mut EsASender) {
+    drop(unsafe { ASender::<Payload, Payload>::from_raw(sender.cast()) })
+}
+
+/// Drop the sender of `B`, destroying the payload of the other sender if it
+/// has been sent.
+///
+/// # Safety
+///
+/// `sender` must be obtained from [`es_either_new`] and not consumed yet.
+#[no_mangle]
+pub unsafe extern "C" fn es_either_drop_b(sender: *mut EsBSender) {
+    drop(unsafe { BSender::<Payload, Payload>::from_raw(sender.cast()) })
+}
+
+/// Create an array slot of `n` senders, which are taken by
+/// [`es_array_sender`], or return null if `n` is zero.
+///
+/// A null handle must not be passed to the other functions.
+#[no_mangle]
+pub extern "C" fn es_array_new(n: usize) -> *mut EsArray {
+    if n == 0 {
+        return ptr::null_mut();
+    }
+    let senders = crate::vec::<Payload>(n).map(Some).collect::<Vec<_>>();
+    Box::into_raw(Box::new(senders)).cast()
+}
+
+/// Take the sender at `i` out of `array`, or return null if `i` is out of
+/// bounds or the sender is already taken.
+///
+/// # Safety
+///
+/// `array` must be obtained from [`es_array_new`] and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn es_array_sender(array: *mut EsArray, i: usize) -> *mut EsArraySender {
+    let senders = unsafe { &mut *array.cast::<Vec<Option<ArraySender>>>() };
+    match senders.get_mut(i).and_then(Option::take) {
+        Some(sender) => Box::into_raw(Box::new(sender)).cast(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Free `array`, dropping the senders not taken yet.
+///
+/// # Safety
+///
+/// `array` must be obtained from [`es_array_new`] and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn es_array_free(array: *mut EsArray) {
+    drop(unsafe { Box::from_raw(array.cast::<Vec<Option<ArraySender>>>()) })
+}
+
+/// Send `payload` through the sender, consuming the sender.
+///
+/// - On [`EsStatus::Sent`], the slot takes the ownership of `payload`.
+/// - On [`EsStatus::Received`], the slot is completed, and the resulting
+///   iterator is written to `out`.
+///
+/// # Safety
+///
+/// - `sender` must be obtained from [`es_array_sender`] and not consumed yet.
+/// - `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn es_array_send(
+    sender: *mut EsArraySender,
+    payload: EsPayload,
+    out: *mut *mut EsIter,
+) -> EsStatus {
+    let sender = unsafe { Box::from_raw(sender.cast::<ArraySender>()) };
+    match sender.send(Payload(payload)) {
+        Ok(()) => EsStatus::Sent,
+        Err(iter) => {
+            unsafe { out.write(Box::into_raw(Box::new(iter)).cast()) };
+            EsStatus::Received
+        }
+    }
+}
+
+/// Drop the sender, leaving its element empty. If it is the last sender, the
+/// payloads in the slot are destroyed.
+///
+/// # Safety
+///
+/// `sender` must be obtained from [`es_array_sender`] and not consumed yet.
+#[no_mangle]
+pub unsafe extern "C" fn es_array_drop_sender(sender: *mut EsArraySender) {
+    drop(unsafe { Box::from_raw(sender.cast::<ArraySender>()) })
+}
+
+/// Write the next payload of `iter` to `out` and return `true`, or return
+/// `false` if the iterator is exhausted.
+///
+/// The ownership of the payload is transferred to the caller.
+///
+/// # Safety
+///
+/// - `iter` must be obtained from [`es_array_send`] and not freed yet.
+/// - `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn es_iter_next(iter: *mut EsIter, out: *mut EsPayload) -> bool {
+    let iter = unsafe { &mut *iter.cast::<Iter>() };
+    match iter.next() {
+        Some(payload) => {
+            unsafe { out.write(payload.into_raw()) };
+            true
+        }
+        None => false,
+    }
+}
+
+/// Free `iter`, destroying the remaining payloads.
+///
+/// # Safety
+///
+/// `iter` must be obtained from [`es_array_send`] and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn es_iter_free(iter: *mut EsIter) {
+    drop(unsafe { Box::from_raw(iter.cast::<Iter>()) })
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use core::{
+        ffi::c_void,
+        ptr,
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    };
+    use std::format;
+
+    use super::*;
+
+    /// Count the destroyed payloads on the counter the payload points to.
+    unsafe extern "C" fn count(data: *mut c_void) {
+        unsafe { &*data.cast::<AtomicUsize>() }.fetch_add(1, Relaxed);
+    }
+
+    fn payload(counter: &AtomicUsize) -> EsPayload {
+        EsPayload {
+            data: ptr::from_ref(counter).cast_mut().cast(),
+            drop: Some(count),
+        }
+    }
+
+    #[test]
+    fn either() {
+        let [c1, c2] = [const { AtomicUsize::new(0) }; 2];
+        let mut a = ptr::null_mut();
+        let mut b = ptr::null_mut();
+        let mut out = payload(&c1);
+        unsafe {
+            es_either_new(&mut a, &mut b);
+            assert_eq!(es_either_send_b(b, payload(&c2), &mut out), EsStatus::Sent);
+            let status = es_either_send_a(a, payload(&c1), &mut out);
+            assert_eq!(status, EsStatus::Received);
+            assert_eq!(out.data, payload(&c2).data);
+
+            es_either_new(&mut a, &mut b);
+            es_either_drop_b(b);
+            let status = es_either_send_a(a, payload(&c1), &mut out);
+            assert_eq!(status, EsStatus::Disconnected);
+
+            es_either_new(&mut a, &mut b);
+            assert_eq!(es_either_send_a(a, payload(&c1), &mut out), EsStatus::Sent);
+            es_either_drop_b(b);
+        }
+        assert_eq!((c1.load(Relaxed), c2.load(Relaxed)), (1, 0));
+    }
+
+    #[test]
+    fn array() {
+        let [c0, c1, c2] = [const { AtomicUsize::new(0) }; 3];
+        assert!(es_array_new(0).is_null());
+        unsafe {
+            let array = es_array_new(3);
+            let s0 = es_array_sender(array, 0);
+            let s2 = es_array_sender(array, 2);
+            assert!(es_array_sender(array, 2).is_null());
+            assert!(es_array_sender(array, 3).is_null());
+            // Drops the sender at 1.
+            es_array_free(array);
+
+            let mut iter = ptr::null_mut();
+            assert_eq!(es_array_send(s2, payload(&c2), &mut iter), EsStatus::Sent);
+            let status = es_array_send(s0, payload(&c0), &mut iter);
+            assert_eq!(status, EsStatus::Received);
+
+            let mut out = payload(&c1);
+            assert!(es_iter_next(iter, &mut out));
+            assert_eq!(out.data, payload(&c0).data);
+            es_iter_free(iter);
+        }
+        let counts = [&c0, &c1, &c2].map(|c| c.load(Relaxed));
+        assert_eq!(counts, [0, 0, 1]);
+    }
+
+    #[test]
+    fn header() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let mut generated = alloc::vec::Vec::new();
+        cbindgen::Builder::new()
+            .with_crate(dir)
+            .with_config(cbindgen::Config::from_file(format!("{dir}/cbindgen.toml")).unwrap())
+            .generate()
+            .unwrap()
+            .write(&mut generated);
+
+        let path = format!("{dir}/include/either_slot.h");
+        let header = std::fs::read(&path).unwrap_or_default();
+        if header != generated {
+            if std::env::var_os("UPDATE_HEADER").is_some() {
+                std::fs::write(&path, generated).unwrap();
+            } else {
+                panic!("`{path}` is outdated, rerun the test with `UPDATE_HEADER=1`");
+            }
+        }
+    }
+}
@@ -0,0 +1,470 @@
+//! Chained slots, whose completion is forwarded into a sender of a parent slot.
+//!
+//! For tree-shaped joins, the resulting values of a child slot are mapped and
+//! sent upstream automatically by whichever sender completes it, whether it
+//! sends its value or is dropped.
+//!
+//! The root sender of a tree is wrapped by [`root`], so that the completion of
+//! the root slot is kept for its owner if a dropped sender completes it.
+
+use alloc::boxed::Box;
+use core::{fmt, mem::MaybeUninit, ptr::NonNull};
+
+use crate::{include::*, SlotSender};
+
+/// A slot sender that can give up placing any value while still obtaining the
+/// completion if it is the last one.
+pub trait Abandon: SlotSender {
+    /// Drop the sender without placing any value, and obtain the completion
+    /// if no other senders exist any longer.
+    fn abandon(self) -> Option<Self::Completion>;
+}
+
+/// A slot sender that can place a value without returning the completion,
+/// which is kept where the owner of the forwarding tree can retrieve it
+/// instead.
+///
+/// It is implemented by [`Root`] and [`Forward`], so that every parent
+/// of a forwarding slot leads to a [`Root`].
+pub trait Keep: SlotSender {
+    /// Place the value into the slot, keeping the completion for the owner of
+    /// the forwarding tree if no other senders exist any longer.
+    fn keep(self, item: Self::Item);
+}
+
+/// The completion of a root slot, kept if no sender receives it.
+struct Shelf<C> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<C>>,
+}
+
+/// The completion is placed on the shelf.
+const KEPT: u8 = 0b001;
+/// The [`Root`] is consumed or dropped.
+const ROOT_GONE: u8 = 0b010;
+/// The [`Orphan`] is dropped.
+const ORPHAN_GONE: u8 = 0b100;
+
+/// Release the shelf with `flag`, and deallocate it if the other side is
+/// gone as well.
+///
+/// # Safety
+///
+/// `shelf` must be valid, and `flag` must be released only once. If `flag` is
+/// [`ORPHAN_GONE`], the kept completion must not be taken yet.
+unsafe fn release<C>(shelf: NonNull<Shelf<C>>, flag: u8) {
+    // SAFETY: See the contract.
+    let state = unsafe { shelf.as_ref() }.state.fetch_or(flag, AcqRel);
+    let other = if flag == ORPHAN_GONE {
+        ROOT_GONE
+    } else {
+        ORPHAN_GONE
+    };
+    if state & other == 0 {
+        return;
+    }
+    // SAFETY: Both sides are gone, so we now own the shelf; a completion kept
+    // but not taken is dropped along with it.
+    let shelf = unsafe { Box::from_raw(shelf.as_ptr()) };
+    if (state | flag) & KEPT != 0 {
+        shelf
+            .value
+            .with_mut(|value| unsafe { (*value).assume_init_drop() });
+    }
+}
+
+/// The root sender of a forwarding tree.
+///
+/// See [`root`] for more information.
+pub struct Root<S: SlotSender> {
+    sender: ManuallyDrop<S>,
+    shelf: NonNull<Shelf<S::Completion>>,
+}
+
+// SAFETY: The completion on the shelf is only moved between the root and the
+// orphan.
+unsafe impl<S> Send for Root<S>
+where
+    S: SlotSender + Send,
+    S::Completion: Send,
+{
+}
+
+impl<S: SlotSender> Root<S> {
+    fn into_parts(self) -> (S, NonNull<Shelf<S::Completion>>) {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again.
+        (unsafe { ManuallyDrop::take(&mut this.sender) }, this.shelf)
+    }
+
+    /// Place the value into the root slot, or obtain the completion if no
+    /// other senders exist any longer.
+    pub fn send(self, item: S::Item) -> Result<(), S::Completion> {
+        let (sender, shelf) = self.into_parts();
+        // SAFETY: The root is consumed here.
+        unsafe { release(shelf, ROOT_GONE) };
+        sender.send(item)
+    }
+}
+
+impl<S: SlotSender + fmt::Debug> fmt::Debug for Root<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Root")
+            .field("sender", &*self.sender)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: SlotSender> Drop for Root<S> {
+    fn drop(&mut self) {
+        // SAFETY: The root is dropped here, and `sender` is never used again.
+        unsafe { release(self.shelf, ROOT_GONE) };
+        unsafe { ManuallyDrop::drop(&mut self.sender) }
+    }
+}
+
+impl<S: SlotSender> SlotSender for Root<S> {
+    type Item = S::Item;
+    type Completion = S::Completion;
+
+    fn send(self, item: S::Item) -> Result<(), S::Completion> {
+        Root::send(self, item)
+    }
+}
+
+impl<S: SlotSender> Keep for Root<S> {
+    fn keep(self, item: S::Item) {
+        let (sender, shelf) = self.into_parts();
+        let Err(completion) = sender.send(item) else {
+            // SAFETY: The root is consumed here.
+            return unsafe { release(shelf, ROOT_GONE) };
+        };
+        // SAFETY: Only the root writes to the shelf, which is read by the orphan only
+        // after `KEPT` is set.
+        unsafe { shelf.as_ref() }
+            .value
+            .with_mut(|value| unsafe { (*value).write(completion) });
+        unsafe { release(shelf, KEPT | ROOT_GONE) }
+    }
+}
+
+/// The owner's handle of the completion of a root slot, if a dropped sender
+/// of the forwarding tree completes it.
+///
+/// See [`root`] for more information.
+pub struct Orphan<C> {
+    shelf: NonNull<Shelf<C>>,
+    taken: bool,
+}
+
+// SAFETY: The completion on the shelf is only moved between the root and the
+// orphan.
+unsafe impl<C: Send> Send for Orphan<C> {}
+
+impl<C> Orphan<C> {
+    /// Take the completion of the root slot, if a dropped sender of the
+    /// forwarding tree has completed it.
+    ///
+    /// Returns `None` if the root slot is not completed yet, or its completion
+    /// is returned by a sender, or it is already taken.
+    pub fn take(&mut self) -> Option<C> {
+        // SAFETY: The shelf is valid until we are dropped.
+        let shelf = unsafe { self.shelf.as_ref() };
+        if self.taken || shelf.state.load(Acquire) & KEPT == 0 {
+            return None;
+        }
+        self.taken = true;
+        // SAFETY: The completion is written before `KEPT` is set, and only taken once.
+        Some(
+            shelf
+                .value
+                .with_mut(|value| unsafe { (*value).assume_init_read() }),
+        )
+    }
+}
+
+impl<C> fmt::Debug for Orphan<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Orphan").finish_non_exhaustive()
+    }
+}
+
+impl<C> Drop for Orphan<C> {
+    fn drop(&mut self) {
+        if self.taken {
+            // SAFETY: The completion is already taken, so the root is gone and never
+            // touches the shelf again; we now own it.
+            drop(unsafe { Box::from_raw(self.shelf.as_ptr()) });
+        } else {
+            // SAFETY: The orphan is dropped here.
+            unsafe { release(self.shelf, ORPHAN_GONE) }
+        }
+    }
+}
+
+/// Wrap the root sender of a forwarding tree, whose completion is kept for
+/// the returned [`Orphan`] if a dropped sender completes the root slot.
+///
+/// Senders of the tree can only be dropped when a [`Root`] is at the top, so
+/// that no completion is discarded silently: it is either returned by the
+/// sender completing the root slot, or kept for the owner of the tree.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::forward::root;
+///
+/// let [r, other] = either_slot::array::<u32, 2>();
+/// let (r, mut orphan) = root(r);
+/// let mut leaves = either_slot::array::vec_forwarding(2, r, |iter| iter.sum::<u32>());
+/// let [l1, l2] = std::array::from_fn(|_| leaves.next().unwrap());
+///
+/// other.send(10).unwrap();
+/// l1.send(1).unwrap();
+/// // The last leaf is dropped, completing the root slot.
+/// drop(l2);
+/// assert_eq!(orphan.take().unwrap().collect::<Vec<_>>(), [1, 10]);
+/// ```
+pub fn root<S: SlotSender>(sender: S) -> (Root<S>, Orphan<S::Completion>) {
+    let shelf = Box::new(Shelf {
+        state: AtomicU8::new(0),
+        value: UnsafeCell::new(MaybeUninit::uninit()),
+    });
+    let shelf = NonNull::from(Box::leak(shelf));
+    let root = Root {
+        sender: ManuallyDrop::new(sender),
+        shelf,
+    };
+    (
+        root,
+        Orphan {
+            shelf,
+            taken: false,
+        },
+    )
+}
+
+/// The one-shot hook of a child slot, only consumed by the sender completing
+/// it.
+pub(crate) struct Hook<Parent, F, A: Allocator = DefaultAlloc> {
+    parent: Parent,
+    map: F,
+    /// The allocator of this structure, taken out when deallocating.
+    alloc: A,
+}
+
+impl<Parent, F, A: Allocator> Hook<Parent, F, A> {
+    const LAYOUT: Layout = Layout::new::<Self>();
+
+    pub(crate) fn new_in(parent: Parent, map: F, alloc: A) -> NonNull<Self> {
+        let memory = match alloc.allocate(Self::LAYOUT) {
+            Ok(memory) => memory.cast::<Self>(),
+            Err(_) => handle_alloc_error(Self::LAYOUT),
+        };
+        // SAFETY: We own this fresh uninitialized memory whose layout is the same as
+        // this type.
+        unsafe { memory.as_ptr().write(Hook { parent, map, alloc }) }
+        memory
+    }
+
+    /// Take the parent sender and the map out of the hook, deallocating it.
+    ///
+    /// # Safety
+    ///
+    /// `this` must point to a valid `Hook`, and must not be used again. Since
+    /// the child slot completes only once, calling this function with its
+    /// completion satisfies the contract.
+    unsafe fn take(this: NonNull<Self>) -> (Parent, F) {
+        // SAFETY: See the contract. The hook is moved out before the deallocation, so
+        // a panicking `map` leaks nothing.
+        let Hook { parent, map, alloc } = unsafe { this.as_ptr().read() };
+        unsafe { alloc.deallocate(this.cast(), Self::LAYOUT) };
+        (parent, map)
+    }
+
+    /// Map the completion of the child slot and send it to the parent sender.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`Hook::take`].
+    unsafe fn forward<C>(this: NonNull<Self>, completion: C) -> Result<(), Parent::Completion>
+    where
+        Parent: SlotSender,
+        F: FnOnce(C) -> Parent::Item,
+    {
+        // SAFETY: See the contract.
+        let (parent, map) = unsafe { Self::take(this) };
+        parent.send(map(completion))
+    }
+
+    /// Map the completion of the child slot and send it to the parent sender,
+    /// keeping the completion of the parent slot for the owner of the tree.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`Hook::take`].
+    unsafe fn keep<C>(this: NonNull<Self>, completion: C)
+    where
+        Parent: Keep,
+        F: FnOnce(C) -> Parent::Item,
+    {
+        // SAFETY: See the contract.
+        let (parent, map) = unsafe { Self::take(this) };
+        parent.keep(map(completion))
+    }
+}
+
+/// A sender of a child slot, whose completion is mapped by `F` and sent by
+/// the `Parent` sender.
+///
+/// The completion of the child slot is forwarded whether the last sender sends
+/// its value or is dropped.
+///
+/// # Dropped senders
+///
+/// If the sender completing the child slot is dropped, and the forwarding
+/// completes the root slot of the tree as well, there is no sender to return
+/// the completion of the root slot to, so it is kept by the [`Root`] for its
+/// [`Orphan`] instead. A sender may also give up by [`Abandon::abandon`],
+/// which returns the completion of the parent slot if any.
+///
+/// The hook holding the parent sender and the map is allocated in `A`.
+///
+/// See [`array::from_place_forwarding`](crate::array::from_place_forwarding)
+/// and [`tuple::tuple_forwarding`](crate::tuple::tuple_forwarding) for more
+/// information.
+pub struct Forward<S, Parent, F, A = DefaultAlloc>
+where
+    S: Abandon,
+    Parent: Keep,
+    F: FnOnce(S::Completion) -> Parent::Item,
+    A: Allocator,
+{
+    sender: ManuallyDrop<S>,
+    hook: NonNull<Hook<Parent, F, A>>,
+}
+
+// SAFETY: The parent sender and the map are only moved out by the sender
+// completing the child slot, which may be on any thread.
+unsafe impl<S, Parent, F, A> Send for Forward<S, Parent, F, A>
+where
+    S: Abandon + Send,
+    Parent: Keep + Send,
+    F: FnOnce(S::Completion) -> Parent::Item + Send,
+    A: Allocator + Send,
+{
+}
+
+impl<S, Parent, F, A> Forward<S, Parent, F, A>
+where
+    S: Abandon,
+    Parent: Keep,
+    F: FnOnce(S::Completion) -> Parent::Item,
+    A: Allocator,
+{
+    /// # Safety
+    ///
+    /// `hook` must be shared by all the senders of the child slot of `sender`
+    /// only, and be valid until it is consumed.
+    pub(crate) unsafe fn new(sender: S, hook: NonNull<Hook<Parent, F, A>>) -> Self {
+        Forward {
+            sender: ManuallyDrop::new(sender),
+            hook,
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> (S, NonNull<Hook<Parent, F, A>>) {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again.
+        (unsafe { ManuallyDrop::take(&mut this.sender) }, this.hook)
+    }
+
+    /// Place the value into the child slot, forwarding its completion to the
+    /// parent sender if no other senders exist any longer, and obtain the
+    /// completion of the parent slot if the forwarding completes it.
+    pub fn send(self, item: S::Item) -> Result<(), Parent::Completion> {
+        let (sender, hook) = self.into_parts();
+        match sender.send(item) {
+            Ok(()) => Ok(()),
+            // SAFETY: We are the last sender of the child slot.
+            Err(completion) => unsafe { Hook::forward(hook, completion) },
+        }
+    }
+}
+
+impl<S, Parent, F, A> fmt::Debug for Forward<S, Parent, F, A>
+where
+    S: Abandon + fmt::Debug,
+    Parent: Keep,
+    F: FnOnce(S::Completion) -> Parent::Item,
+    A: Allocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Forward")
+            .field("sender", &*self.sender)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, Parent, F, A> Drop for Forward<S, Parent, F, A>
+where
+    S: Abandon,
+    Parent: Keep,
+    F: FnOnce(S::Completion) -> Parent::Item,
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        // SAFETY: `sender` is never used again.
+        let sender = unsafe { ManuallyDrop::take(&mut self.sender) };
+        if let Some(completion) = sender.abandon() {
+            // SAFETY: We are the last sender of the child slot.
+            unsafe { Hook::keep(self.hook, completion) }
+        }
+    }
+}
+
+impl<S, Parent, F, A> SlotSender for Forward<S, Parent, F, A>
+where
+    S: Abandon,
+    Parent: Keep,
+    F: FnOnce(S::Completion) -> Parent::Item,
+    A: Allocator,
+{
+    type Item = S::Item;
+    type Completion = Parent::Completion;
+
+    fn send(self, item: S::Item) -> Result<(), Parent::Completion> {
+        Forward::send(self, item)
+    }
+}
+
+impl<S, Parent, F, A> Abandon for Forward<S, Parent, F, A>
+where
+    S: Abandon,
+    Parent: Keep,
+    F: FnOnce(S::Completion) -> Parent::Item,
+    A: Allocator,
+{
+    fn abandon(self) -> Option<Parent::Completion> {
+        let (sender, hook) = self.into_parts();
+        // SAFETY: We are the last sender of the child slot.
+        let completion = sender.abandon()?;
+        unsafe { Hook::forward(hook, completion) }.err()
+    }
+}
+
+impl<S, Parent, F, A> Keep for Forward<S, Parent, F, A>
+where
+    S: Abandon,
+    Parent: Keep,
+    F: FnOnce(S::Completion) -> Parent::Item,
+    A: Allocator,
+{
+    fn keep(self, item: S::Item) {
+        let (sender, hook) = self.into_parts();
+        if let Err(completion) = sender.send(item) {
+            // SAFETY: We are the last sender of the child slot.
+            unsafe { Hook::keep(hook, completion) }
+        }
+    }
+}
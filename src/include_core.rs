@@ -1,12 +1,91 @@
+#[cfg(feature = "alloc")]
 pub use alloc::alloc::{handle_alloc_error, Global};
+#[cfg(all(
+    feature = "alloc",
+    not(any(feature = "portable-atomic", feature = "critical-section"))
+))]
+pub use core::sync::atomic::AtomicPtr;
+#[cfg(all(
+    target_has_atomic = "32",
+    not(any(feature = "portable-atomic", feature = "critical-section"))
+))]
+pub use core::sync::atomic::AtomicU32;
+#[cfg(all(
+    target_has_atomic = "64",
+    not(any(feature = "portable-atomic", feature = "critical-section"))
+))]
+pub use core::sync::atomic::AtomicU64;
+#[cfg(not(any(feature = "portable-atomic", feature = "critical-section")))]
+pub use core::sync::atomic::{self, AtomicBool, AtomicU8, AtomicUsize, Ordering::*};
 pub use core::{
     alloc::{Allocator, Layout},
     hint,
     mem::{self, ManuallyDrop},
     ptr::NonNull,
-    sync::atomic::{self, AtomicBool, AtomicU8, AtomicUsize, Ordering::*},
 };
 
+// Targets without native atomic CAS or RMW operations, e.g. thumbv6m, route
+// the atomics through `portable-atomic`, or emulate them by critical sections,
+// which takes precedence if both are enabled.
+#[cfg(all(
+    feature = "alloc",
+    feature = "portable-atomic",
+    not(feature = "critical-section")
+))]
+pub use portable_atomic::AtomicPtr;
+#[cfg(all(
+    target_has_atomic = "64",
+    feature = "portable-atomic",
+    not(feature = "critical-section")
+))]
+pub use portable_atomic::AtomicU64;
+#[cfg(all(feature = "portable-atomic", not(feature = "critical-section")))]
+pub use portable_atomic::{
+    self as atomic, AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering::*,
+};
+
+#[cfg(all(feature = "alloc", feature = "critical-section"))]
+pub use crate::cs_atomic::AtomicPtr;
+#[cfg(feature = "critical-section")]
+pub use crate::cs_atomic::{
+    atomic, AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering::*,
+};
+
+/// The default allocator of the slots.
+#[cfg(feature = "alloc")]
+pub type DefaultAlloc = Global;
+/// The default allocator of the slots without the `alloc` feature, which can
+/// never be constructed.
+///
+/// Slots built without a heap always name their allocators explicitly, e.g.
+/// [`Intrusive`](crate::intrusive::Intrusive), so enabling the `alloc` feature
+/// elsewhere in the dependency graph, which turns the default into [`Global`],
+/// never changes the types of their senders.
+#[cfg(not(feature = "alloc"))]
+pub type DefaultAlloc = NoAlloc;
+
+/// The placeholder allocator of the slots without the `alloc` feature.
+#[cfg(not(feature = "alloc"))]
+#[derive(Debug)]
+pub enum NoAlloc {}
+
+// SAFETY: The allocator can never be constructed.
+#[cfg(not(feature = "alloc"))]
+unsafe impl Allocator for NoAlloc {
+    fn allocate(&self, _: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        match *self {}
+    }
+
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
+        match *self {}
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+pub fn handle_alloc_error(layout: Layout) -> ! {
+    panic!("memory allocation of {} bytes failed", layout.size())
+}
+
 #[derive(Debug)]
 pub(crate) struct UnsafeCell<T: ?Sized>(core::cell::UnsafeCell<T>);
 
@@ -15,6 +94,15 @@ impl<T> UnsafeCell<T> {
         UnsafeCell(core::cell::UnsafeCell::new(data))
     }
 
+    #[cfg(feature = "alloc")]
+    pub(crate) fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+
+    pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+        f(self.0.get())
+    }
+
     pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
         f(self.0.get())
     }
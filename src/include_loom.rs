@@ -9,15 +9,20 @@ pub use loom::{
     alloc::{alloc, dealloc, Layout},
     cell::UnsafeCell,
     hint,
-    sync::atomic::{self, AtomicBool, AtomicU8, AtomicUsize, Ordering::*},
+    sync::atomic::{
+        self, AtomicBool, AtomicPtr, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering::*,
+    },
 };
 
+#[derive(Debug, Clone, Copy)]
 pub struct Global;
 
+pub type DefaultAlloc = Global;
+
 unsafe impl Allocator for Global {
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
         match layout.size() {
-            0 => Ok(NonNull::slice_from_raw_parts(layout.dangling(), 0)),
+            0 => Ok(NonNull::slice_from_raw_parts(layout.dangling_ptr(), 0)),
             // SAFETY: `layout` is non-zero in size,
             size => unsafe {
                 let raw_ptr = alloc(layout);
@@ -0,0 +1,299 @@
+//! Intrusive slots embedded in caller-owned structures.
+//!
+//! Intrusive slots own their storage in place instead of allocating it, and
+//! their senders borrow the slot. Once the senders of a slot are all consumed
+//! or dropped, the slot can be reused.
+//!
+//! The slots are const-constructible, so they can also be placed in `static`s
+//! and hand out `'static` senders, which requires no heap at all.
+
+use core::{alloc::AllocError, cell, mem::MaybeUninit};
+
+use crate::{
+    array::{self, Element},
+    include::*,
+    ASender, BSender,
+};
+
+/// The allocator of intrusive slots, which hands out the storage embedded in
+/// the slot.
+///
+/// The user can't construct this type directly, see [`EitherSlot`] and
+/// [`ArraySlot`] instead.
+#[derive(Debug)]
+pub struct Intrusive<'a> {
+    storage: NonNull<u8>,
+    layout: Layout,
+    busy: &'a AtomicBool,
+}
+
+// SAFETY: The storage is only accessed by the slot algorithm, and the busy flag
+// is atomic.
+unsafe impl Send for Intrusive<'_> {}
+
+// SAFETY: The storage is handed out only once each time the slot is occupied,
+// and released when it is deallocated.
+unsafe impl Allocator for Intrusive<'_> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() > self.layout.size() || layout.align() > self.layout.align() {
+            return Err(AllocError);
+        }
+        Ok(NonNull::slice_from_raw_parts(self.storage, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
+        // The `Release` ordering publishes every access to the storage to the next
+        // occupation of the slot.
+        self.busy.store(false, Release);
+    }
+}
+
+/// The storage of a slot, shared by the intrusive slots.
+struct Storage<T> {
+    busy: AtomicBool,
+    inner: cell::UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T> Storage<T> {
+    const_new! {
+        fn new() -> Self {
+            Storage {
+                busy: AtomicBool::new(false),
+                inner: cell::UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }
+    }
+
+    fn occupy(&self) -> Option<Intrusive<'_>> {
+        // The `Acquire` ordering observes every access to the storage during the last
+        // occupation of the slot.
+        self.busy
+            .compare_exchange(false, true, Acquire, Relaxed)
+            .ok()?;
+        Some(Intrusive {
+            storage: NonNull::new(self.inner.get()).unwrap().cast(),
+            layout: Layout::new::<T>(),
+            busy: &self.busy,
+        })
+    }
+
+    fn is_busy(&self) -> bool {
+        self.busy.load(Relaxed)
+    }
+}
+
+/// The senders of an [`EitherSlot`].
+pub type EitherSenders<'a, A, B> = (ASender<A, B, Intrusive<'a>>, BSender<A, B, Intrusive<'a>>);
+
+/// An intrusive [`either`](crate::either()) slot.
+///
+/// The slot can be embedded in other structures, e.g. pinned or reference
+/// counted ones, so that no allocation is needed for creating senders. If the
+/// senders are leaked, the slot stays occupied forever, and the values in it
+/// are leaked as well.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::{intrusive::EitherSlot, SendError};
+///
+/// let slot = EitherSlot::new();
+/// let (a, b) = slot.senders().unwrap();
+/// assert!(slot.senders().is_none());
+///
+/// a.send(1).unwrap();
+/// assert_eq!(b.send('x'), Err(SendError::Received('x', 1)));
+///
+/// // The slot is reusable after completion.
+/// let (a, b) = slot.senders().unwrap();
+/// # drop((a, b));
+/// ```
+pub struct EitherSlot<A, B> {
+    storage: Storage<crate::Inner<A, B, Intrusive<'static>>>,
+}
+
+// SAFETY: The same as the senders.
+unsafe impl<A: Send, B: Send> Send for EitherSlot<A, B> {}
+unsafe impl<A: Send, B: Send> Sync for EitherSlot<A, B> {}
+
+impl<A, B> core::fmt::Debug for EitherSlot<A, B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EitherSlot")
+            .field("busy", &self.is_busy())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A, B> Default for EitherSlot<A, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, B> EitherSlot<A, B> {
+    const_new! {
+        /// Create a new vacant slot.
+        pub fn new() -> Self {
+            EitherSlot {
+                storage: Storage::new(),
+            }
+        }
+    }
+
+    /// Create the senders of the slot, or return `None` if the senders created
+    /// last time are not all consumed or dropped yet.
+    pub fn senders(&self) -> Option<EitherSenders<'_, A, B>> {
+        self.storage.occupy().map(crate::either_in)
+    }
+
+    /// Check if the slot is occupied by senders.
+    pub fn is_busy(&self) -> bool {
+        self.storage.is_busy()
+    }
+}
+
+/// The senders of an [`ArraySlot`].
+pub type ArraySenders<'a, T, const N: usize> =
+    [array::Sender<T, [Element<T>; N], Intrusive<'a>>; N];
+
+/// An intrusive [`array`](crate::array()) slot.
+///
+/// See [`EitherSlot`] for more information.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::intrusive::ArraySlot;
+///
+/// let slot = ArraySlot::<_, 2>::new();
+/// for round in 0..2 {
+///     let [s1, s2] = slot.senders().unwrap();
+///     s1.send(round).unwrap();
+///     let iter = s2.send(round + 1).unwrap_err();
+///     assert_eq!(iter.collect::<Vec<_>>(), [round, round + 1]);
+/// }
+/// ```
+pub struct ArraySlot<T, const N: usize> {
+    storage: Storage<array::Inner<T, [Element<T>; N], Intrusive<'static>>>,
+}
+
+// SAFETY: The same as the senders.
+unsafe impl<T: Send, const N: usize> Send for ArraySlot<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for ArraySlot<T, N> {}
+
+impl<T, const N: usize> core::fmt::Debug for ArraySlot<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ArraySlot")
+            .field("busy", &self.is_busy())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, const N: usize> Default for ArraySlot<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> ArraySlot<T, N> {
+    const_new! {
+        /// Create a new vacant slot.
+        pub fn new() -> Self {
+            ArraySlot {
+                storage: Storage::new(),
+            }
+        }
+    }
+
+    /// Create the senders of the slot, or return `None` if the senders created
+    /// last time are not all consumed or dropped yet.
+    pub fn senders(&self) -> Option<ArraySenders<'_, T, N>> {
+        self.storage.occupy().map(array::array_in)
+    }
+
+    /// Check if the slot is occupied by senders.
+    pub fn is_busy(&self) -> bool {
+        self.storage.is_busy()
+    }
+}
+
+/// An [`EitherSlot`] placed in a `static`, which hands out `'static` senders.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::intrusive::StaticEither;
+///
+/// static SLOT: StaticEither<u32, char> = StaticEither::new();
+///
+/// for _ in 0..2 {
+///     let (a, b) = SLOT.senders().unwrap();
+///     let j = std::thread::spawn(move || a.send(1));
+///     let res = b.send('x');
+///     assert!(res.is_ok() != j.join().unwrap().is_ok());
+///     // Re-armed after completion.
+///     assert!(!SLOT.is_busy());
+/// }
+/// ```
+pub type StaticEither<A, B> = EitherSlot<A, B>;
+
+/// An [`ArraySlot`] placed in a `static`, which hands out `'static` senders.
+///
+/// See [`StaticEither`] for more information.
+pub type StaticArray<T, const N: usize> = ArraySlot<T, N>;
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use alloc::{sync::Arc, vec::Vec};
+    use std::thread;
+
+    use super::{ArraySlot, EitherSlot, StaticArray};
+    use crate::SendError;
+
+    #[test]
+    fn reuse() {
+        let slot = EitherSlot::new();
+        let (a, b) = slot.senders().unwrap();
+        assert!(slot.is_busy());
+        drop(a);
+        assert!(slot.senders().is_none());
+        assert_eq!(b.send(1), Err(SendError::Disconnected(1)));
+        assert!(!slot.is_busy());
+
+        let (a, b) = slot.senders().unwrap();
+        b.send(2).unwrap();
+        assert_eq!(a.send('a'), Err(SendError::Received('a', 2)));
+    }
+
+    #[test]
+    fn send() {
+        let task = Arc::new(ArraySlot::<usize, 3>::new());
+        for _ in 0..10 {
+            let res = thread::scope(|s| {
+                let j = task
+                    .senders()
+                    .unwrap()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, sender)| s.spawn(move || sender.send(i)))
+                    .collect::<Vec<_>>();
+                j.into_iter()
+                    .map(|j| j.join().unwrap())
+                    .fold(Ok(()), Result::and)
+            });
+            assert_eq!(res.unwrap_err().collect::<Vec<_>>(), [0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn statics() {
+        static SLOT: StaticArray<u32, 2> = StaticArray::new();
+
+        for round in 0..3 {
+            let [s1, s2] = SLOT.senders().unwrap();
+            let j = thread::spawn(move || s1.send(round));
+            let res = s2.send(round + 1).and(j.join().unwrap());
+            assert_eq!(res.unwrap_err().collect::<Vec<_>>(), [round, round + 1]);
+        }
+    }
+}
@@ -1,23 +1,101 @@
-#![doc = include_str!("../README.md")]
+// The examples of the README allocate their slots on the heap.
+#![cfg_attr(feature = "alloc", doc = include_str!("../README.md"))]
 #![no_std]
 #![feature(allocator_api)]
 #![feature(trusted_len)]
-#![cfg_attr(loom, feature(alloc_layout_extra))]
-#![cfg_attr(test, feature(assert_matches))]
 
+/// Define `new` as a `const fn`, except under loom whose atomics are not
+/// const-constructible.
+macro_rules! const_new {
+    ($(#[$meta:meta])* $vis:vis fn new($($arg:ident: $ty:ty),*) -> Self $body:block) => {
+        #[cfg(not(loom))]
+        $(#[$meta])*
+        $vis const fn new($($arg: $ty),*) -> Self $body
+
+        #[cfg(loom)]
+        $(#[$meta])*
+        $vis fn new($($arg: $ty),*) -> Self $body
+    };
+}
+
+#[cfg(all(test, feature = "alloc", not(loom)))]
+mod bomb;
+#[cfg(all(feature = "critical-section", not(loom)))]
+mod cs_atomic;
 #[cfg_attr(not(loom), path = "include_core.rs")]
 #[cfg_attr(loom, path = "include_loom.rs")]
 mod include;
+#[cfg(feature = "alloc")]
+mod lock;
 
 pub mod array;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "alloc")]
+pub mod forward;
+pub mod intrusive;
+#[cfg(feature = "alloc")]
+pub mod local;
+#[cfg(feature = "alloc")]
+pub mod pool;
+pub mod scope;
+#[cfg(all(target_has_atomic = "8", target_has_atomic = "ptr"))]
+pub mod shm;
 pub mod tuple;
+pub mod word;
+
+/// Derive a struct slot, whose senders and result are named after the fields.
+///
+/// For a struct `Foo`, the derivation generates:
+///
+/// - `FooSenders`, whose fields are the senders of the respective fields;
+/// - `FooPartial`, whose fields are the optional values of the respective
+///   fields, obtained from the last sender;
+/// - `Foo::senders()`, which creates the slot and returns its `FooSenders`;
+/// - `TryFrom<FooPartial> for Foo`, which succeeds if all the values are
+///   placed.
+///
+/// `FooPartial` implements [`Debug`](core::fmt::Debug) if all the field types
+/// do. The struct can have at most 32 fields.
+///
+/// The generated code refers to this crate as `::either_slot`, which can be
+/// overridden by `#[either_slot(crate = path::to::either_slot)]`, e.g. if the
+/// crate is re-exported by another one.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::EitherSlot;
+///
+/// #[derive(Debug, PartialEq, EitherSlot)]
+/// struct Resp {
+///     user: String,
+///     perms: u32,
+/// }
+///
+/// let RespSenders { user, perms } = Resp::senders();
+/// assert!(user.send("root".into()).is_ok());
+/// let Err(partial) = perms.send(0o755) else { unreachable!() };
+/// assert_eq!(partial.user.as_deref(), Some("root"));
+///
+/// let resp = Resp::try_from(partial).unwrap();
+/// assert_eq!(resp, Resp { user: "root".into(), perms: 0o755 });
+/// ```
+#[cfg(feature = "derive")]
+pub use either_slot_derive::EitherSlot;
 
 use self::include::*;
+#[cfg(feature = "alloc")]
 pub use self::{
     array::{array, vec},
     tuple::tuple,
 };
+pub use self::{
+    scope::{scope, Scope},
+    word::either_word,
+};
 
+#[cfg(any(feature = "alloc", test))]
 extern crate alloc;
 
 #[cfg(test)]
@@ -35,26 +113,33 @@ const HAS_A: u8 = 2;
 const HAS_B: u8 = 3;
 const DONE: u8 = 4;
 
-struct Inner<A, B> {
+struct Inner<A, B, Alloc: Allocator = DefaultAlloc> {
     state: AtomicU8,
     place: UnsafeCell<Place<A, B>>,
+    alloc: ManuallyDrop<Alloc>,
 }
 
-impl<A, B> Inner<A, B> {
+impl<A, B, Alloc: Allocator> Inner<A, B, Alloc> {
     const LAYOUT: Layout = Layout::new::<Self>();
 
-    fn new() -> NonNull<Self> {
-        let memory = match Global.allocate(Self::LAYOUT) {
+    fn new_in(alloc: Alloc) -> NonNull<Self> {
+        let memory = match alloc.allocate(Self::LAYOUT) {
             Ok(memory) => memory.cast::<Self>(),
             Err(_) => handle_alloc_error(Self::LAYOUT),
         };
         let value = Self {
             state: AtomicU8::new(INIT),
             place: UnsafeCell::new(Place { uninit: () }),
+            alloc: ManuallyDrop::new(alloc),
         };
         unsafe { memory.as_ptr().write(value) }
         memory
     }
+
+    unsafe fn deallocate(this: NonNull<Self>) {
+        let alloc = unsafe { ManuallyDrop::take(&mut (*this.as_ptr()).alloc) };
+        unsafe { alloc.deallocate(this.cast(), Self::LAYOUT) }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -63,17 +148,109 @@ pub enum SendError<P, Q> {
     Disconnected(P),
 }
 
-#[derive(Debug)]
-pub struct ASender<A, B>(NonNull<Inner<A, B>>);
+/// The common interface of the senders of all kinds of slots, so that generic
+/// code can feed any slot.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// use either_slot::SlotSender;
+///
+/// fn work<S: SlotSender<Item = u32>>(sender: S, input: u32) -> Option<S::Completion> {
+///     match input {
+///         0 => {
+///             sender.abstain();
+///             None
+///         }
+///         _ => sender.send(input * 2).err(),
+///     }
+/// }
+///
+/// let (a, b) = either_slot::either::<u32, u32>();
+/// assert!(work(a, 1).is_none());
+/// assert!(work(b, 2).is_some());
+///
+/// let [s1, s2] = either_slot::array::<u32, 2>();
+/// assert!(work(s1, 0).is_none());
+/// assert!(work(s2, 3).unwrap().eq([6]));
+///
+/// let (s1, s2) = either_slot::tuple::<(u32, u32)>();
+/// assert!(work(s2, 4).is_none());
+/// assert_eq!(work(s1, 5), Some((Some(10), Some(8))));
+///
+/// let mut senders = either_slot::array::reduce(2, 0, |acc, x| *acc += x);
+/// let (s1, s2) = (senders.next().unwrap(), senders.next().unwrap());
+/// assert!(work(s1, 6).is_none());
+/// assert_eq!(work(s2, 7), Some(26));
+///
+/// let (s, seal) = either_slot::array::open();
+/// assert!(seal.seal().is_none());
+/// assert!(work(s, 8).unwrap().eq([16]));
+/// # }
+/// ```
+pub trait SlotSender: Sized {
+    /// The type of the value placed by this sender.
+    type Item;
+
+    /// The type obtained if sending doesn't simply place the value, e.g. the
+    /// resulting values of the slot completed by this sender.
+    type Completion;
+
+    /// Place the value into the slot, or obtain the completion.
+    fn send(self, item: Self::Item) -> Result<(), Self::Completion>;
+
+    /// Give up placing any value into the slot, which is the same as dropping
+    /// the sender.
+    fn abstain(self) {
+        drop(self)
+    }
+}
 
 #[derive(Debug)]
-pub struct BSender<A, B>(NonNull<Inner<A, B>>);
+pub struct ASender<A, B, Alloc: Allocator = DefaultAlloc>(NonNull<Inner<A, B, Alloc>>);
 
-unsafe impl<A: Send, B: Send> Send for ASender<A, B> {}
-unsafe impl<A: Send, B: Send> Send for BSender<A, B> {}
+#[derive(Debug)]
+pub struct BSender<A, B, Alloc: Allocator = DefaultAlloc>(NonNull<Inner<A, B, Alloc>>);
+
+unsafe impl<A: Send, B: Send, Alloc: Allocator + Send> Send for ASender<A, B, Alloc> {}
+unsafe impl<A: Send, B: Send, Alloc: Allocator + Send> Send for BSender<A, B, Alloc> {}
+
+impl<A, B, Alloc: Allocator> ASender<A, B, Alloc> {
+    /// Convert the sender into a raw pointer, e.g. to pass it through a C
+    /// callback.
+    ///
+    /// The sender must be converted back by [`ASender::from_raw`], or it is
+    /// leaked.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "alloc")] {
+    /// use either_slot::{ASender, SendError};
+    ///
+    /// let (a, b) = either_slot::either::<u32, char>();
+    /// let raw = a.into_raw();
+    /// b.send('x').unwrap();
+    ///
+    /// // SAFETY: `raw` is obtained from `into_raw` and converted back only once.
+    /// let a = unsafe { ASender::<u32, char>::from_raw(raw) };
+    /// assert_eq!(a.send(1), Err(SendError::Received(1, 'x')));
+    /// # }
+    /// ```
+    pub fn into_raw(self) -> *mut () {
+        ManuallyDrop::new(self).0.as_ptr().cast()
+    }
 
-impl<A, B> ASender<A, B> {
-    const LAYOUT: Layout = Inner::<A, B>::LAYOUT;
+    /// Convert the raw pointer back into the sender.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be obtained from [`ASender::into_raw`] of the same type, and
+    /// converted back only once.
+    pub unsafe fn from_raw(raw: *mut ()) -> Self {
+        ASender(unsafe { NonNull::new_unchecked(raw.cast()) })
+    }
 
     pub fn send(self, a: A) -> Result<(), SendError<A, B>> {
         let inner = unsafe { self.0.as_ref() };
@@ -106,7 +283,7 @@ impl<A, B> ASender<A, B> {
     }
 }
 
-impl<A, B> Drop for ASender<A, B> {
+impl<A, B, Alloc: Allocator> Drop for ASender<A, B, Alloc> {
     fn drop(&mut self) {
         let inner = unsafe { self.0.as_ref() };
         loop {
@@ -122,13 +299,18 @@ impl<A, B> Drop for ASender<A, B> {
                             break;
                         }
                     }
-                    HAS_B => inner
-                        .place
-                        .with_mut(|ptr| unsafe { ManuallyDrop::drop(&mut (*ptr).b) }),
+                    HAS_B => {
+                        let b = inner.place.with_mut(|ptr| unsafe { ptr.read().b });
+                        // Deallocate before dropping the value, so that a panicking
+                        // destructor leaks nothing.
+                        unsafe { Inner::deallocate(self.0) };
+                        drop(ManuallyDrop::into_inner(b));
+                        break;
+                    }
                     DONE => {}
                     _ => unreachable!(),
                 }
-                unsafe { Global.deallocate(self.0.cast(), Self::LAYOUT) };
+                unsafe { Inner::deallocate(self.0) };
                 break;
             }
             hint::spin_loop();
@@ -136,8 +318,32 @@ impl<A, B> Drop for ASender<A, B> {
     }
 }
 
-impl<A, B> BSender<A, B> {
-    const LAYOUT: Layout = Inner::<A, B>::LAYOUT;
+impl<A, B, Alloc: Allocator> SlotSender for ASender<A, B, Alloc> {
+    type Item = A;
+    type Completion = SendError<A, B>;
+
+    fn send(self, item: A) -> Result<(), SendError<A, B>> {
+        ASender::send(self, item)
+    }
+}
+
+impl<A, B, Alloc: Allocator> BSender<A, B, Alloc> {
+    /// Convert the sender into a raw pointer.
+    ///
+    /// See [`ASender::into_raw`] for more information.
+    pub fn into_raw(self) -> *mut () {
+        ManuallyDrop::new(self).0.as_ptr().cast()
+    }
+
+    /// Convert the raw pointer back into the sender.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be obtained from [`BSender::into_raw`] of the same type, and
+    /// converted back only once.
+    pub unsafe fn from_raw(raw: *mut ()) -> Self {
+        BSender(unsafe { NonNull::new_unchecked(raw.cast()) })
+    }
 
     pub fn send(self, b: B) -> Result<(), SendError<B, A>> {
         let inner = unsafe { self.0.as_ref() };
@@ -170,7 +376,7 @@ impl<A, B> BSender<A, B> {
     }
 }
 
-impl<A, B> Drop for BSender<A, B> {
+impl<A, B, Alloc: Allocator> Drop for BSender<A, B, Alloc> {
     fn drop(&mut self) {
         let inner = unsafe { self.0.as_ref() };
         loop {
@@ -186,13 +392,18 @@ impl<A, B> Drop for BSender<A, B> {
                             break;
                         }
                     }
-                    HAS_A => inner
-                        .place
-                        .with_mut(|ptr| unsafe { ManuallyDrop::drop(&mut (*ptr).a) }),
+                    HAS_A => {
+                        let a = inner.place.with_mut(|ptr| unsafe { ptr.read().a });
+                        // Deallocate before dropping the value, so that a panicking
+                        // destructor leaks nothing.
+                        unsafe { Inner::deallocate(self.0) };
+                        drop(ManuallyDrop::into_inner(a));
+                        break;
+                    }
                     DONE => {}
                     _ => unreachable!(),
                 }
-                unsafe { Global.deallocate(self.0.cast(), Self::LAYOUT) };
+                unsafe { Inner::deallocate(self.0) };
                 break;
             }
             hint::spin_loop();
@@ -200,14 +411,30 @@ impl<A, B> Drop for BSender<A, B> {
     }
 }
 
+impl<A, B, Alloc: Allocator> SlotSender for BSender<A, B, Alloc> {
+    type Item = B;
+    type Completion = SendError<B, A>;
+
+    fn send(self, item: B) -> Result<(), SendError<B, A>> {
+        BSender::send(self, item)
+    }
+}
+
+#[cfg(feature = "alloc")]
 pub fn either<A, B>() -> (ASender<A, B>, BSender<A, B>) {
-    let inner = Inner::new();
+    either_in(Global)
+}
+
+pub fn either_in<A, B, Alloc: Allocator>(
+    alloc: Alloc,
+) -> (ASender<A, B, Alloc>, BSender<A, B, Alloc>) {
+    let inner = Inner::new_in(alloc);
     (ASender(inner), BSender(inner))
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
-    use std::assert_matches::assert_matches;
+    use std::assert_matches;
     #[cfg(not(loom))]
     use std::thread;
 
@@ -230,6 +457,65 @@ mod tests {
         let _ = either::<i32, u8>();
     }
 
+    #[cfg(all(feature = "derive", not(loom)))]
+    #[test]
+    fn derive() {
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+        use std::format;
+
+        use crate::{bomb::Bomb, EitherSlot};
+
+        #[derive(EitherSlot)]
+        #[either_slot(crate = crate)]
+        struct Pair<'a, T> {
+            value: T,
+            bomb: Bomb<'a>,
+        }
+
+        let dropped = AtomicUsize::new(0);
+        let PairSenders { value, bomb } = Pair::<u32>::senders();
+        bomb.send(Bomb(false, &dropped)).unwrap();
+        let partial = value.send(1).unwrap_err();
+        assert_eq!(
+            format!("{partial:?}"),
+            "PairPartial { value: Some(1), bomb: Some(Bomb(false, 0)) }"
+        );
+        let pair = Pair::try_from(partial).unwrap();
+        assert_eq!((pair.value, pair.bomb.0), (1, false));
+        drop(pair);
+        assert_eq!(dropped.load(Relaxed), 1);
+
+        // The values are dropped along with the last sender.
+        let PairSenders { value, bomb } = Pair::<&str>::senders();
+        bomb.send(Bomb(false, &dropped)).unwrap();
+        drop(value);
+        assert_eq!(dropped.load(Relaxed), 2);
+
+        let PairSenders { value, bomb } = Pair::senders();
+        drop(bomb);
+        let partial = value.send("a").unwrap_err();
+        assert!(partial.bomb.is_none());
+        assert!(Pair::try_from(partial).is_err());
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn raw() {
+        use crate::{ASender, BSender};
+
+        let (a, b) = either::<u8, char>();
+        let (a, b) = (a.into_raw(), b.into_raw());
+        let b = unsafe { BSender::<u8, char>::from_raw(b) };
+        b.send('b').unwrap();
+        let a = unsafe { ASender::<u8, char>::from_raw(a) };
+        assert_eq!(a.send(1), Err(SendError::Received(1, 'b')));
+
+        let (a, b) = either::<u8, char>();
+        let a = unsafe { ASender::<u8, char>::from_raw(a.into_raw()) };
+        drop(a);
+        assert_eq!(b.send('b'), Err(SendError::Disconnected('b')));
+    }
+
     #[test]
     fn send() {
         fn inner() {
@@ -276,4 +562,22 @@ mod tests {
         #[cfg(loom)]
         loom::model(|| inner());
     }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn drop_panic() {
+        use std::{
+            panic::{self, AssertUnwindSafe},
+            sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        };
+
+        use crate::bomb::Bomb;
+
+        let dropped = AtomicUsize::new(0);
+        let (a, b) = either::<Bomb, ()>();
+        let _ = a.send(Bomb(true, &dropped));
+        let res = panic::catch_unwind(AssertUnwindSafe(move || drop(b)));
+        assert!(res.is_err());
+        assert_eq!(dropped.load(Relaxed), 1);
+    }
 }
@@ -0,0 +1,135 @@
+//! Single-threaded slots without atomic read-modify-write operations.
+//!
+//! The slots in this module mirror [`either`](crate::either()),
+//! [`array`](crate::array()) and [`tuple`](crate::tuple()), but their senders
+//! are `!Send`, and their states are kept in [`Cell`]s and reference counts
+//! instead. The results of sending are the same as their thread-safe
+//! counterparts, so code can switch between them by changing the paths or
+//! type aliases.
+
+pub mod array;
+pub mod tuple;
+
+use alloc::rc::Rc;
+use core::{cell::Cell, fmt};
+
+pub use self::{
+    array::{array, vec},
+    tuple::tuple,
+};
+use crate::{SendError, SlotSender};
+
+enum State<A, B> {
+    Init,
+    A(A),
+    B(B),
+    Done,
+}
+
+pub struct ASender<A, B>(Rc<Cell<State<A, B>>>);
+
+pub struct BSender<A, B>(Rc<Cell<State<A, B>>>);
+
+impl<A, B> fmt::Debug for ASender<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ASender").finish_non_exhaustive()
+    }
+}
+
+impl<A, B> fmt::Debug for BSender<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BSender").finish_non_exhaustive()
+    }
+}
+
+impl<A, B> ASender<A, B> {
+    pub fn send(self, a: A) -> Result<(), SendError<A, B>> {
+        match self.0.replace(State::Done) {
+            State::Init => {
+                self.0.set(State::A(a));
+                Ok(())
+            }
+            State::B(b) => Err(SendError::Received(a, b)),
+            State::Done => Err(SendError::Disconnected(a)),
+            State::A(_) => unreachable!(),
+        }
+    }
+}
+
+impl<A, B> Drop for ASender<A, B> {
+    fn drop(&mut self) {
+        match self.0.replace(State::Done) {
+            // Our own value is left for the other sender.
+            State::A(a) => self.0.set(State::A(a)),
+            state => drop(state),
+        }
+    }
+}
+
+impl<A, B> SlotSender for ASender<A, B> {
+    type Item = A;
+    type Completion = SendError<A, B>;
+
+    fn send(self, item: A) -> Result<(), SendError<A, B>> {
+        ASender::send(self, item)
+    }
+}
+
+impl<A, B> BSender<A, B> {
+    pub fn send(self, b: B) -> Result<(), SendError<B, A>> {
+        match self.0.replace(State::Done) {
+            State::Init => {
+                self.0.set(State::B(b));
+                Ok(())
+            }
+            State::A(a) => Err(SendError::Received(b, a)),
+            State::Done => Err(SendError::Disconnected(b)),
+            State::B(_) => unreachable!(),
+        }
+    }
+}
+
+impl<A, B> Drop for BSender<A, B> {
+    fn drop(&mut self) {
+        match self.0.replace(State::Done) {
+            // Our own value is left for the other sender.
+            State::B(b) => self.0.set(State::B(b)),
+            state => drop(state),
+        }
+    }
+}
+
+impl<A, B> SlotSender for BSender<A, B> {
+    type Item = B;
+    type Completion = SendError<B, A>;
+
+    fn send(self, item: B) -> Result<(), SendError<B, A>> {
+        BSender::send(self, item)
+    }
+}
+
+pub fn either<A, B>() -> (ASender<A, B>, BSender<A, B>) {
+    let inner = Rc::new(Cell::new(State::Init));
+    (ASender(inner.clone()), BSender(inner))
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::either;
+    use crate::SendError;
+
+    #[test]
+    fn basic() {
+        let (a, b) = either();
+        a.send(1).unwrap();
+        assert_eq!(b.send('x'), Err(SendError::Received('x', 1)));
+
+        let (a, b) = either::<_, ()>();
+        drop(b);
+        assert_eq!(a.send(1), Err(SendError::Disconnected(1)));
+
+        let (a, b) = either::<alloc::string::String, u8>();
+        b.send(1).unwrap();
+        drop(a);
+    }
+}
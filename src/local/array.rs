@@ -0,0 +1,474 @@
+//! Single-threaded array slots.
+//!
+//! See [`crate::array`](mod@crate::array) for the thread-safe counterpart.
+
+use alloc::{
+    boxed::Box,
+    rc::Rc,
+    vec::{self, Vec},
+};
+use core::{
+    array,
+    cell::Cell,
+    iter::{FusedIterator, TrustedLen},
+    marker::PhantomData,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ptr,
+};
+
+use crate::SlotSender;
+
+/// The storage of elements in the single-threaded slot, whose placed flag is
+/// a plain [`Cell`].
+///
+/// See [`crate::array::Element`] for more information.
+pub type Element<T> = crate::array::Element<T, Cell<bool>>;
+
+/// The custom storage place of [`Element`]s in the single-threaded slot.
+///
+/// See [`crate::array::Place`] for more information.
+pub trait Place<T>: AsRef<[Element<T>]> {}
+impl<T, P> Place<T> for P where P: AsRef<[Element<T>]> {}
+
+#[derive(Debug)]
+struct Inner<T, P: Place<T>> {
+    place: P,
+    /// The arrival tickets of the elements, only allocated in arrival-order
+    /// mode.
+    tickets: Option<Box<[Cell<usize>]>>,
+    /// The ticket taken by the next arriving value.
+    arrived: Cell<usize>,
+    marker: PhantomData<[T]>,
+}
+
+impl<T, P: Place<T>> Inner<T, P> {
+    fn new(place: P, ordered: bool) -> Rc<Self> {
+        let len = place.as_ref().len();
+        assert!(len != 0, "the slot must not be empty");
+        Rc::new(Inner {
+            place,
+            tickets: ordered.then(|| (0..len).map(|_| Cell::new(0)).collect()),
+            arrived: Cell::new(0),
+            marker: PhantomData,
+        })
+    }
+
+    /// Give the value just placed at `index` its arrival ticket, if the slot is
+    /// in arrival-order mode.
+    fn arrive(&self, index: usize) {
+        if let Some(tickets) = &self.tickets {
+            let ticket = self.arrived.get();
+            tickets[index].set(ticket);
+            self.arrived.set(ticket + 1);
+        }
+    }
+
+    /// The arrival ticket of the value placed at `index`, or `None` if the slot
+    /// is not in arrival-order mode.
+    fn ticket(&self, index: usize) -> Option<usize> {
+        self.tickets.as_ref().map(|tickets| tickets[index].get())
+    }
+}
+
+/// The placer of a single-threaded array slot.
+///
+/// The user can only access the slot once by this structure.
+#[derive(Debug)]
+pub struct Sender<T, P: Place<T>> {
+    inner: Rc<Inner<T, P>>,
+    index: usize,
+}
+
+impl<T, P: Place<T>> Sender<T, P> {
+    /// Place the value into the slot, or obtain the resulting iterator if no
+    /// other senders exist any longer.
+    pub fn send(self, value: T) -> Result<(), SenderIter<T, P>> {
+        self.send_in_place(|storage| storage.write(value))
+    }
+
+    /// Place the value returned by `f` into the slot, or obtain the resulting
+    /// iterator if no other senders exist any longer.
+    ///
+    /// If `f` panics, no value is placed and this sender is considered dropped.
+    pub fn send_with(self, f: impl FnOnce() -> T) -> Result<(), SenderIter<T, P>> {
+        self.send_in_place(|storage| storage.write(f()))
+    }
+
+    /// Initialize the value directly in the storage of the slot by `init`, or
+    /// obtain the resulting iterator if no other senders exist any longer.
+    ///
+    /// See [`crate::array::Sender::send_in_place`] for more information.
+    pub fn send_in_place(
+        self,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> Result<(), SenderIter<T, P>> {
+        let elem = &self.inner.place.as_ref()[self.index];
+        // SAFETY: Each sender has its ownership of one `Element` storage in its
+        // `inner`. If `init` panics, we are dropped with no value placed.
+        if unsafe { elem.place_with(init) } {
+            self.inner.arrive(self.index);
+        }
+
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again.
+        let inner = unsafe { ptr::read(&this.inner) };
+        match Rc::strong_count(&inner) {
+            1 => Err(SenderIter { inner, index: 0 }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T, P: Place<T>> Drop for Sender<T, P> {
+    fn drop(&mut self) {
+        if Rc::strong_count(&self.inner) == 1 {
+            drop(SenderIter {
+                inner: self.inner.clone(),
+                index: 0,
+            })
+        }
+    }
+}
+
+impl<T, P: Place<T>> SlotSender for Sender<T, P> {
+    type Item = T;
+    type Completion = SenderIter<T, P>;
+
+    fn send(self, item: T) -> Result<(), SenderIter<T, P>> {
+        Sender::send(self, item)
+    }
+}
+
+/// The resulting iterator of values that all the senders have placed into the
+/// single-threaded slot.
+///
+/// See [`crate::array::SenderIter`] for more information.
+#[derive(Debug)]
+pub struct SenderIter<T, P: Place<T>> {
+    inner: Rc<Inner<T, P>>,
+    index: usize,
+}
+
+impl<T, P: Place<T>> SenderIter<T, P> {
+    fn next_ranked(&mut self) -> Option<(Option<usize>, usize, T)> {
+        while let Some(elem) = self.inner.place.as_ref().get(self.index) {
+            let index = self.index;
+            self.index += 1;
+            // SAFETY: We now owns `inner`; each element is only taken once since
+            // `index` is incremented at every yield.
+            if let Some(data) = unsafe { elem.take() } {
+                return Some((self.inner.ticket(index), index, data));
+            }
+        }
+        None
+    }
+
+    /// Convert the iterator into one yielding the remaining values in the order
+    /// their senders arrived.
+    ///
+    /// See [`crate::array::SenderIter::by_arrival`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut senders = either_slot::local::array::vec_ordered(3);
+    /// let (s1, s2, s3) = (
+    ///     senders.next().unwrap(),
+    ///     senders.next().unwrap(),
+    ///     senders.next().unwrap(),
+    /// );
+    /// s3.send('c').unwrap();
+    /// s1.send('a').unwrap();
+    /// let iter = s2.send('b').unwrap_err();
+    /// assert_eq!(iter.by_arrival().collect::<String>(), "cab");
+    /// ```
+    pub fn by_arrival(mut self) -> ByArrival<T, P> {
+        let rest = self
+            .inner
+            .place
+            .as_ref()
+            .get(self.index..)
+            .unwrap_or_default();
+        let mut order = (self.index..)
+            .zip(rest)
+            // SAFETY: We now owns `inner`.
+            .filter(|&(_, elem)| unsafe { elem.get() }.is_some())
+            .map(|(index, _)| (self.inner.ticket(index), index))
+            .collect::<Vec<_>>();
+        order.sort_by_key(|&(rank, _)| rank);
+
+        // The remaining values are now dropped by the new iterator.
+        self.index = self.inner.place.as_ref().len();
+        ByArrival {
+            iter: self,
+            order: order.into_iter(),
+        }
+    }
+
+    /// Convert the iterator into one yielding the remaining values in index
+    /// order, alongside their arrival ranks and indices, in the form of
+    /// `(arrival_rank, index, value)`.
+    ///
+    /// See [`crate::array::SenderIter::ranked`] for more information.
+    pub fn ranked(self) -> Ranked<T, P> {
+        Ranked(self)
+    }
+}
+
+impl<T, P: Place<T>> Iterator for SenderIter<T, P> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ranked().map(|(_, _, data)| data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.inner.place.as_ref().len() - self.index;
+        (0, Some(len))
+    }
+}
+
+impl<T, P: Place<T>> FusedIterator for SenderIter<T, P> {}
+
+impl<T, P: Place<T>> Drop for SenderIter<T, P> {
+    fn drop(&mut self) {
+        // If dropping a value panics, the guard drops the remaining values during
+        // unwinding.
+        struct Guard<'a, T, P: Place<T>>(&'a mut SenderIter<T, P>);
+
+        impl<T, P: Place<T>> Drop for Guard<'_, T, P> {
+            fn drop(&mut self) {
+                self.0.for_each(drop)
+            }
+        }
+
+        let guard = Guard(self);
+        guard.0.for_each(drop);
+    }
+}
+
+/// The resulting iterator of values in arrival order.
+///
+/// See [`SenderIter::by_arrival`] for more information.
+#[derive(Debug)]
+pub struct ByArrival<T, P: Place<T>> {
+    iter: SenderIter<T, P>,
+    order: vec::IntoIter<(Option<usize>, usize)>,
+}
+
+impl<T, P: Place<T>> Iterator for ByArrival<T, P> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, index) = self.order.next()?;
+        // SAFETY: We now owns `inner`; each element is only taken once since it is
+        // removed from `order`.
+        unsafe { self.iter.inner.place.as_ref()[index].take() }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.order.size_hint()
+    }
+}
+
+impl<T, P: Place<T>> ExactSizeIterator for ByArrival<T, P> {}
+
+impl<T, P: Place<T>> FusedIterator for ByArrival<T, P> {}
+
+impl<T, P: Place<T>> Drop for ByArrival<T, P> {
+    fn drop(&mut self) {
+        /// The unwinding guard dropping the remaining values.
+        struct Rest<'a, T, P: Place<T>>(&'a mut ByArrival<T, P>);
+
+        impl<T, P: Place<T>> Drop for Rest<'_, T, P> {
+            fn drop(&mut self) {
+                self.0.for_each(drop)
+            }
+        }
+
+        while let Some(value) = self.next() {
+            // If dropping the value panics, the guard drops the remaining values during
+            // unwinding.
+            let guard = Rest(self);
+            drop(value);
+            mem::forget(guard);
+        }
+    }
+}
+
+/// The resulting iterator of values alongside their arrival ranks and indices.
+///
+/// See [`SenderIter::ranked`] for more information.
+#[derive(Debug)]
+pub struct Ranked<T, P: Place<T>>(SenderIter<T, P>);
+
+impl<T, P: Place<T>> Iterator for Ranked<T, P> {
+    type Item = (Option<usize>, usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_ranked()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T, P: Place<T>> FusedIterator for Ranked<T, P> {}
+
+/// The initialization iterator for senders of a single-threaded slot.
+///
+/// See [`crate::array::InitIter`] for more information.
+#[derive(Debug)]
+pub struct InitIter<T, P: Place<T>> {
+    /// The reference of the last sender, handed out on its yield.
+    inner: Option<Rc<Inner<T, P>>>,
+    index: usize,
+}
+
+impl<T, P: Place<T>> Iterator for InitIter<T, P> {
+    type Item = Sender<T, P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = self.inner.as_ref()?.place.as_ref().len();
+        let inner = if self.index + 1 < len {
+            self.inner.clone()?
+        } else {
+            self.inner.take()?
+        };
+        let s = Sender {
+            inner,
+            index: self.index,
+        };
+        self.index += 1;
+        Some(s)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self
+            .inner
+            .as_ref()
+            .map_or(0, |inner| inner.place.as_ref().len() - self.index);
+        (len, Some(len))
+    }
+}
+
+impl<T, P: Place<T>> Drop for InitIter<T, P> {
+    fn drop(&mut self) {
+        self.for_each(drop)
+    }
+}
+
+impl<T, P: Place<T>> ExactSizeIterator for InitIter<T, P> {}
+
+impl<T, P: Place<T>> FusedIterator for InitIter<T, P> {}
+
+unsafe impl<T, P: Place<T>> TrustedLen for InitIter<T, P> {}
+
+/// Construct an iterator of senders to a single-threaded slot, whose values
+/// will be placed on `place`.
+pub fn from_place<T, P: Place<T>>(place: P) -> InitIter<T, P> {
+    InitIter {
+        inner: Some(Inner::new(place, false)),
+        index: 0,
+    }
+}
+
+/// Construct an iterator of senders to a single-threaded slot, whose values
+/// will be placed on a [`Vec`].
+pub fn vec<T>(count: usize) -> InitIter<T, Vec<Element<T>>> {
+    from_place(Element::vec(count))
+}
+
+/// Construct an iterator of senders to a single-threaded slot in arrival-order
+/// mode, whose values will be placed on `place`.
+///
+/// See [`crate::array::from_place_ordered`] for more information.
+pub fn from_place_ordered<T, P: Place<T>>(place: P) -> InitIter<T, P> {
+    InitIter {
+        inner: Some(Inner::new(place, true)),
+        index: 0,
+    }
+}
+
+/// Construct an iterator of senders to a single-threaded slot in arrival-order
+/// mode, whose values will be placed on a [`Vec`].
+///
+/// See [`crate::array::from_place_ordered`] for more information.
+pub fn vec_ordered<T>(count: usize) -> InitIter<T, Vec<Element<T>>> {
+    from_place_ordered(Element::vec(count))
+}
+
+/// Construct an array of senders to a single-threaded slot, whose values will
+/// be placed on an array.
+///
+/// # Examples
+///
+/// ```rust
+/// let [s1, s2, s3] = either_slot::local::array();
+/// s1.send(1).unwrap();
+/// drop(s2);
+/// let iter = s3.send(3).unwrap_err();
+/// assert_eq!(iter.collect::<Vec<_>>(), [1, 3]);
+/// ```
+pub fn array<T, const N: usize>() -> [Sender<T, [Element<T>; N]>; N] {
+    let inner = Inner::new(Element::array(), false);
+    array::from_fn(|index| Sender {
+        inner: inner.clone(),
+        index,
+    })
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{vec, vec_ordered};
+
+    #[test]
+    fn send() {
+        let mut senders = vec(3);
+        let s1 = senders.next().unwrap();
+        s1.send(1).unwrap();
+        let s2 = senders.next().unwrap();
+        let s3 = senders.next().unwrap();
+        assert!(senders.next().is_none());
+        drop(senders);
+
+        s3.send(3).unwrap();
+        let iter = s2.send(2).unwrap_err();
+        assert_eq!(iter.collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn drop_all() {
+        let senders = vec::<alloc::string::String>(3).collect::<Vec<_>>();
+        let mut senders = senders.into_iter();
+        senders.next().unwrap().send("a".into()).unwrap();
+        drop(senders);
+    }
+
+    #[test]
+    fn ordered() {
+        let senders = vec_ordered(4).collect::<Vec<_>>();
+        let mut senders = senders.into_iter();
+        let (s0, s1, s2, s3) = (
+            senders.next().unwrap(),
+            senders.next().unwrap(),
+            senders.next().unwrap(),
+            senders.next().unwrap(),
+        );
+        s2.send('c').unwrap();
+        drop(s0);
+        s3.send('d').unwrap();
+        let iter = s1.send('b').unwrap_err();
+        assert!(iter
+            .ranked()
+            .eq([(Some(2), 1, 'b'), (Some(0), 2, 'c'), (Some(1), 3, 'd')]));
+
+        let [s0, s1] = super::array();
+        s1.send(1).unwrap();
+        let iter = s0.send(0).unwrap_err();
+        assert_eq!(iter.by_arrival().collect::<Vec<_>>(), [0, 1]);
+    }
+}
@@ -0,0 +1,231 @@
+//! Single-threaded tuple slots.
+//!
+//! See [`crate::tuple`](mod@crate::tuple) for the thread-safe counterpart.
+
+use alloc::rc::Rc;
+use core::{
+    cell::Cell,
+    fmt,
+    mem::{ManuallyDrop, MaybeUninit},
+    ptr,
+};
+
+use crate::{
+    tuple::{Concat, CountAt, InElement, Position, Split, Tuple, TupleList},
+    SlotSender,
+};
+
+/// The whole tuple list of concatenated head, current and tail tuples.
+type List<Head, Current, Tail> = <Head as Position<Current, Tail, Cell<bool>>>::List;
+
+/// The error type of the returned value of [`send`](Sender::send); a tuple of
+/// [`Option`]s of stored values.
+///
+/// See [`crate::tuple::Take`] for more information.
+pub type Take<Head, Current, Tail> =
+    <<List<Head, Current, Tail> as InElement<Cell<bool>>>::Take as TupleList>::Tuple;
+
+struct Inner<T: InElement<Cell<bool>>> {
+    place: T::Place,
+}
+
+/// The placer of a single-threaded tuple slot.
+///
+/// See [`crate::tuple::Sender`] for more information.
+pub struct Sender<Head, Current, Tail>(Rc<Inner<List<Head, Current, Tail>>>)
+where
+    Head: Position<Current, Tail, Cell<bool>>;
+
+impl<Head, Current, Tail> fmt::Debug for Sender<Head, Current, Tail>
+where
+    Head: Position<Current, Tail, Cell<bool>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<Head, Current, Tail> Sender<Head, Current, Tail>
+where
+    Head: Position<Current, Tail, Cell<bool>>,
+{
+    /// The index of the target element in the whole tuple.
+    pub const INDEX: usize = Head::INDEX;
+
+    /// The length of the whole tuple.
+    pub const LEN: usize = Head::LEN;
+
+    /// Place the value into the slot, or obtain the resulting tuple if no
+    /// other senders exist any longer.
+    pub fn send(self, value: Current) -> Result<(), Take<Head, Current, Tail>> {
+        self.send_in_place(|storage| storage.write(value))
+    }
+
+    /// Place the value returned by `f` into the slot, or obtain the resulting
+    /// tuple if no other senders exist any longer.
+    ///
+    /// If `f` panics, no value is placed and this sender is considered dropped.
+    pub fn send_with(self, f: impl FnOnce() -> Current) -> Result<(), Take<Head, Current, Tail>> {
+        self.send_in_place(|storage| storage.write(f()))
+    }
+
+    /// Initialize the value directly in the storage of the slot by `init`, or
+    /// obtain the resulting tuple if no other senders exist any longer.
+    ///
+    /// See [`crate::array::Sender::send_in_place`] for more information.
+    pub fn send_in_place(
+        self,
+        init: impl FnOnce(&mut MaybeUninit<Current>) -> &mut Current,
+    ) -> Result<(), Take<Head, Current, Tail>> {
+        let elem = Head::element(&self.0.place);
+        // SAFETY: Each sender has its ownership of one `Element` storage in its
+        // `inner`. If `init` panics, we are dropped with no value placed.
+        unsafe { elem.place_with(init) };
+
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again.
+        let inner = unsafe { ptr::read(&this.0) };
+        match Rc::strong_count(&inner) {
+            // SAFETY: We now owns `inner`.
+            1 => Err(unsafe {
+                <List<Head, Current, Tail> as InElement<Cell<bool>>>::take(&inner.place)
+            }
+            .into_tuple()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<Head, Current, Tail> Drop for Sender<Head, Current, Tail>
+where
+    Head: Position<Current, Tail, Cell<bool>>,
+{
+    fn drop(&mut self) {
+        if Rc::strong_count(&self.0) == 1 {
+            // SAFETY: We now owns `inner`.
+            drop(unsafe {
+                <List<Head, Current, Tail> as InElement<Cell<bool>>>::take(&self.0.place)
+            })
+        }
+    }
+}
+
+impl<Head, Current, Tail> SlotSender for Sender<Head, Current, Tail>
+where
+    Head: Position<Current, Tail, Cell<bool>>,
+{
+    type Item = Current;
+    type Completion = Take<Head, Current, Tail>;
+
+    fn send(self, item: Current) -> Result<(), Take<Head, Current, Tail>> {
+        Sender::send(self, item)
+    }
+}
+
+/// The placer of a single-threaded tuple slot, targeting the element at index
+/// `I` of the whole tuple `T`.
+///
+/// See [`crate::tuple::SenderAt`] for more information.
+pub type SenderAt<T, const I: usize> = Sender<
+    <<T as Tuple>::TupleList as Split<CountAt<I>>>::Head,
+    <<T as Tuple>::TupleList as Split<CountAt<I>>>::Current,
+    <<T as Tuple>::TupleList as Split<CountAt<I>>>::Tail,
+>;
+
+/// A tuple type that is constructible into its single-threaded tuple slot
+/// type.
+pub trait Construct: Tuple
+where
+    Self::TupleList: InElement<Cell<bool>>,
+{
+    /// The constructed tuple list of senders. The returned result type of
+    /// [`tuple()`] is the corresponding tuple type.
+    type Sender: TupleList;
+
+    #[allow(private_interfaces)]
+    #[doc(hidden)]
+    fn construct(inner: &Rc<Inner<Self::TupleList>>) -> Self::Sender;
+}
+
+impl<T> Construct for T
+where
+    T: Tuple,
+    T::TupleList: InElement<Cell<bool>> + ConstructList<(), T::TupleList>,
+{
+    type Sender = <T::TupleList as ConstructList<(), T::TupleList>>::Sender;
+
+    #[allow(private_interfaces)]
+    fn construct(inner: &Rc<Inner<Self::TupleList>>) -> Self::Sender {
+        <T::TupleList as ConstructList<(), T::TupleList>>::construct(inner)
+    }
+}
+
+/// A tuple list of the elements following `Prefix` in the whole tuple list
+/// `W`, which is constructible into their single-threaded senders.
+#[doc(hidden)]
+pub trait ConstructList<Prefix, W: InElement<Cell<bool>>>: TupleList {
+    type Sender: TupleList;
+
+    #[allow(private_interfaces)]
+    fn construct(inner: &Rc<Inner<W>>) -> Self::Sender;
+}
+
+impl<Prefix, W: InElement<Cell<bool>>> ConstructList<Prefix, W> for () {
+    type Sender = ();
+
+    #[allow(private_interfaces)]
+    fn construct(_: &Rc<Inner<W>>) {}
+}
+
+impl<Prefix, Head, Tail, W> ConstructList<Prefix, W> for (Head, Tail)
+where
+    (Head, Tail): TupleList,
+    Prefix: Position<Head, Tail::Tuple, Cell<bool>, List = W> + Concat<(Head,)>,
+    Tail: ConstructList<<Prefix as Concat<(Head,)>>::Output, W>,
+    W: InElement<Cell<bool>>,
+    (Sender<Prefix, Head, Tail::Tuple>, Tail::Sender): TupleList,
+{
+    type Sender = (Sender<Prefix, Head, Tail::Tuple>, Tail::Sender);
+
+    #[allow(private_interfaces)]
+    fn construct(inner: &Rc<Inner<W>>) -> Self::Sender {
+        (Sender(inner.clone()), Tail::construct(inner))
+    }
+}
+
+/// Create a single-threaded tuple slot, and return a tuple of senders
+/// targeting their own respective element in the slot.
+///
+/// # Examples
+///
+/// ```rust
+/// let (s1, s2, s3) = either_slot::local::tuple::<(&str, u8, char)>();
+/// s1.send("1").unwrap();
+/// drop(s2);
+/// let ret = s3.send('3').unwrap_err();
+/// assert_eq!(ret, (Some("1"), None, Some('3')));
+/// ```
+pub fn tuple<T>() -> <T::Sender as TupleList>::Tuple
+where
+    T: Construct,
+    <T as Tuple>::TupleList: InElement<Cell<bool>>,
+{
+    let inner = Rc::new(Inner {
+        place: <T::TupleList as InElement<Cell<bool>>>::init(),
+    });
+    T::construct(&inner).into_tuple()
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::tuple;
+
+    #[test]
+    fn send() {
+        let (s1, s2, s3) = tuple::<(i32, u8, char)>();
+        s2.send(2).unwrap();
+        s1.send(1).unwrap();
+        let res = s3.send('3');
+        assert_eq!(res, Err((Some(1), Some(2), Some('3'))));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::include::*;
+
+/// A tiny spin lock guarding short critical sections in slots.
+#[derive(Debug)]
+pub(crate) struct SpinLock<T> {
+    locked: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: The data is only accessed by one thread at a time under the lock.
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    const_new! {
+        pub(crate) fn new(data: T) -> Self {
+            SpinLock {
+                locked: AtomicBool::new(false),
+                data: UnsafeCell::new(data),
+            }
+        }
+    }
+
+    /// Run `f` with exclusive access to the data.
+    ///
+    /// The lock is released even if `f` panics, so that other threads can
+    /// still make progress.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        while self
+            .locked
+            .compare_exchange(false, true, Acquire, Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+
+        struct Unlock<'a>(&'a AtomicBool);
+        impl Drop for Unlock<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Release);
+            }
+        }
+        let _unlock = Unlock(&self.locked);
+
+        // SAFETY: The lock grants us exclusive access to the data.
+        self.data.with_mut(|ptr| f(unsafe { &mut *ptr }))
+    }
+
+    pub(crate) fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
@@ -0,0 +1,356 @@
+//! Pooled allocation of slots.
+
+use core::{alloc::AllocError, ptr};
+
+use crate::include::*;
+
+/// The count of distinct layouts a pool caches blocks for; blocks of further
+/// layouts are allocated and freed by the global allocator directly.
+const BUCKETS: usize = 8;
+
+/// The default maximum count of blocks cached for each layout.
+const DEFAULT_CAPACITY: usize = 64;
+
+/// The bits of the alignment exponent in the key of a layout.
+const ALIGN_BITS: u32 = 6;
+
+/// The key of a layout whose blocks can be cached, or `None` if they are too
+/// small to hold the link of the free list.
+///
+/// The key is never 0, which marks an unclaimed bucket.
+fn key(layout: Layout) -> Option<usize> {
+    (layout.size() >= mem::size_of::<*mut u8>() && layout.size() <= usize::MAX >> ALIGN_BITS)
+        .then(|| layout.size() << ALIGN_BITS | layout.align().trailing_zeros() as usize)
+}
+
+/// The layout of the blocks cached under `key`.
+fn layout(key: usize) -> Layout {
+    let align = 1 << (key & ((1 << ALIGN_BITS) - 1));
+    // SAFETY: The key is built from a valid layout.
+    unsafe { Layout::from_size_align_unchecked(key >> ALIGN_BITS, align) }
+}
+
+/// A lock-free free list of memory blocks of the same layout, linked through
+/// the first word of each block.
+#[derive(Debug)]
+struct Bucket {
+    /// The key of the layout of the cached blocks, or 0 if the bucket is not
+    /// claimed yet. A claimed bucket is never released.
+    key: AtomicUsize,
+    head: AtomicPtr<u8>,
+    len: AtomicUsize,
+    /// Whether a thread is popping from the list. Pops are exclusive, which
+    /// rules out the ABA problem of the stack, since a block cannot be popped
+    /// and pushed back while another thread is reading its link.
+    popping: AtomicBool,
+}
+
+impl Bucket {
+    const_new! {
+        fn new() -> Self {
+            Bucket {
+                key: AtomicUsize::new(0),
+                head: AtomicPtr::new(ptr::null_mut()),
+                len: AtomicUsize::new(0),
+                popping: AtomicBool::new(false),
+            }
+        }
+    }
+
+    /// Push a block onto the list, or return `false` if the list is full.
+    ///
+    /// # Safety
+    ///
+    /// The block must be allocated by the global allocator with the layout of
+    /// this bucket, and not be used by anyone else.
+    unsafe fn push(&self, block: NonNull<u8>, capacity: usize) -> bool {
+        if self.len.fetch_add(1, Relaxed) >= capacity {
+            self.len.fetch_sub(1, Relaxed);
+            return false;
+        }
+        let mut head = self.head.load(Relaxed);
+        loop {
+            // SAFETY: The block is large enough to hold the link, and is owned by
+            // us until it is published.
+            unsafe { block.as_ptr().cast::<*mut u8>().write_unaligned(head) };
+            match (self.head).compare_exchange_weak(head, block.as_ptr(), Release, Relaxed) {
+                Ok(_) => return true,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Pop a block from the list, or return `None` if the list is empty or
+    /// another thread is popping.
+    fn pop(&self) -> Option<NonNull<u8>> {
+        if (self.popping)
+            .compare_exchange(false, true, Acquire, Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+        let mut head = self.head.load(Acquire);
+        let block = loop {
+            let Some(block) = NonNull::new(head) else {
+                break None;
+            };
+            // SAFETY: Only we can pop blocks now, so the block stays in the list
+            // until our exchange below, and its link is published by the `Release`
+            // exchange pushing it.
+            let next = unsafe { block.as_ptr().cast::<*mut u8>().read_unaligned() };
+            match (self.head).compare_exchange_weak(head, next, Acquire, Acquire) {
+                Ok(_) => break Some(block),
+                Err(current) => head = current,
+            }
+        };
+        self.popping.store(false, Release);
+        if block.is_some() {
+            self.len.fetch_sub(1, Relaxed);
+        }
+        block
+    }
+
+    /// Free all the blocks in the list.
+    fn clear(&self) {
+        while (self.popping)
+            .compare_exchange(false, true, Acquire, Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let mut head = self.head.swap(ptr::null_mut(), Acquire);
+        self.popping.store(false, Release);
+
+        let layout = layout(self.key.load(Relaxed));
+        while let Some(block) = NonNull::new(head) {
+            // SAFETY: The list is detached, so we own all of its blocks.
+            head = unsafe { block.as_ptr().cast::<*mut u8>().read_unaligned() };
+            // SAFETY: The block is allocated by the global allocator with the layout.
+            unsafe { Global.deallocate(block, layout) };
+            self.len.fetch_sub(1, Relaxed);
+        }
+    }
+}
+
+/// A pool of slot allocations, which caches the memory blocks of finished
+/// slots for the slots of the same layout to come.
+///
+/// A reference to the pool is an [`Allocator`] that can be passed to the
+/// constructors of slots, e.g. [`either_in`](crate::either_in),
+/// [`array_in`](crate::array::array_in) and
+/// [`tuple_in`](crate::tuple::tuple_in). The cached blocks are freed when the
+/// pool is [cleared](Pool::clear) or dropped.
+///
+/// The blocks are cached in lock-free free lists, one for each of the first 8
+/// layouts deallocated into the pool, and each list holds at most a
+/// [capacity](Pool::with_capacity) of blocks. Blocks beyond these limits are
+/// allocated and freed by the global allocator directly, so the pool never
+/// allocates for its own bookkeeping.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::{either_in, pool::Pool};
+///
+/// static POOL: Pool = Pool::new();
+///
+/// for i in 0..10 {
+///     let (a, b) = either_in(&POOL);
+///     a.send(i).unwrap();
+///     assert!(b.send('x').is_err());
+/// }
+/// assert_eq!(POOL.cached(), 1);
+/// ```
+#[derive(Debug)]
+pub struct Pool {
+    buckets: [Bucket; BUCKETS],
+    capacity: usize,
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pool {
+    const_new! {
+        /// Create a new empty pool, caching at most 64 blocks for each layout.
+        pub fn new() -> Self {
+            Pool::with_capacity(DEFAULT_CAPACITY)
+        }
+    }
+
+    /// Create a new empty pool, caching at most `capacity` blocks for each
+    /// layout.
+    #[cfg(not(loom))]
+    pub const fn with_capacity(capacity: usize) -> Self {
+        Pool {
+            buckets: [const { Bucket::new() }; BUCKETS],
+            capacity,
+        }
+    }
+
+    /// Create a new empty pool, caching at most `capacity` blocks for each
+    /// layout.
+    #[cfg(loom)]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Pool {
+            buckets: core::array::from_fn(|_| Bucket::new()),
+            capacity,
+        }
+    }
+
+    /// The bucket caching the blocks of the layout with `key`, claiming a new
+    /// one if `claim` is set.
+    fn bucket(&self, key: usize, claim: bool) -> Option<&Bucket> {
+        for bucket in &self.buckets {
+            // Buckets are claimed in order, so no bucket after an unclaimed one
+            // holds the key.
+            let current = match bucket.key.load(Relaxed) {
+                0 if claim => match bucket.key.compare_exchange(0, key, Relaxed, Relaxed) {
+                    Ok(_) => return Some(bucket),
+                    Err(current) => current,
+                },
+                current => current,
+            };
+            match current {
+                0 => return None,
+                current if current == key => return Some(bucket),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// The count of the memory blocks cached in the pool.
+    pub fn cached(&self) -> usize {
+        (self.buckets.iter())
+            .map(|bucket| bucket.len.load(Relaxed))
+            .sum()
+    }
+
+    /// Free all the memory blocks cached in the pool.
+    pub fn clear(&self) {
+        (self.buckets.iter())
+            .filter(|bucket| bucket.key.load(Relaxed) != 0)
+            .for_each(Bucket::clear)
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.clear()
+    }
+}
+
+// SAFETY: Every memory block is either allocated by the global allocator, or
+// reused from the cache in which it is no longer used by anyone.
+unsafe impl Allocator for Pool {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let cached = key(layout)
+            .and_then(|key| self.bucket(key, false))
+            .and_then(Bucket::pop);
+        match cached {
+            Some(block) => Ok(NonNull::slice_from_raw_parts(block, layout.size())),
+            None => Global.allocate(layout),
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if let Some(bucket) = key(layout).and_then(|key| self.bucket(key, true)) {
+            // SAFETY: The block is allocated with `layout` and is no longer used.
+            if unsafe { bucket.push(ptr, self.capacity) } {
+                return;
+            }
+        }
+        // SAFETY: The block is not cached, and is allocated by the global allocator.
+        unsafe { Global.deallocate(ptr, layout) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(loom))]
+    use std::thread;
+
+    #[cfg(loom)]
+    use loom::{sync::Arc, thread};
+
+    use super::Pool;
+    use crate::array::array_in;
+
+    #[cfg(not(loom))]
+    #[test]
+    fn reuse() {
+        use alloc::vec::Vec;
+
+        use crate::tuple::tuple_in;
+
+        let pool = Pool::new();
+        for i in 0..3 {
+            let (s1, s2) = tuple_in::<(i32, char), _>(&pool);
+            s1.send(i).unwrap();
+            assert_eq!(s2.send('a'), Err((Some(i), Some('a'))));
+
+            let [s1, s2] = array_in(&pool);
+            drop(s1);
+            assert_eq!(s2.send(i).unwrap_err().collect::<Vec<_>>(), [i]);
+        }
+        assert_eq!(pool.cached(), 2);
+        pool.clear();
+        assert_eq!(pool.cached(), 0);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn capacity() {
+        use crate::{either_in, include::*};
+
+        let pool = Pool::with_capacity(1);
+        let (a1, b1) = either_in::<i32, char, _>(&pool);
+        let (a2, b2) = either_in::<i32, char, _>(&pool);
+        a1.send(1).unwrap();
+        a2.send(2).unwrap();
+        drop((b1, b2));
+        assert_eq!(pool.cached(), 1);
+
+        // Blocks too small to hold the link of the free list are never cached,
+        // and neither are the ones of layouts beyond the buckets.
+        let layouts = (0..super::BUCKETS + 1).map(|i| Layout::array::<usize>(i + 2).unwrap());
+        for layout in [Layout::new::<u8>()].into_iter().chain(layouts) {
+            let block = pool.allocate(layout).unwrap();
+            // SAFETY: The block is allocated by the pool with the layout.
+            unsafe { pool.deallocate(block.cast(), layout) };
+        }
+        assert_eq!(pool.cached(), super::BUCKETS);
+    }
+
+    #[test]
+    fn send() {
+        fn inner() {
+            #[cfg(not(loom))]
+            let pool = std::sync::Arc::new(Pool::new());
+            #[cfg(loom)]
+            let pool = Arc::new(Pool::new());
+
+            let p = pool.clone();
+            let j = thread::spawn(move || {
+                let [s1, s2] = array_in(&*p);
+                s1.send(1).unwrap();
+                drop(s2);
+            });
+            let [s1, s2] = array_in(&*pool);
+            s1.send(2).unwrap();
+            drop(s2);
+            j.join().unwrap();
+            // The block of one slot may be reused by the other.
+            assert!((1..=2).contains(&pool.cached()));
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+}
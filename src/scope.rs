@@ -0,0 +1,258 @@
+//! Scoped slots whose storage lives on the stack of [`scope`].
+//!
+//! Slots created in a scope borrow it, so every sender must be consumed or
+//! dropped before the scope returns, and thus the values are free to carry
+//! non-`'static` borrows.
+
+use core::{alloc::AllocError, cell, mem::MaybeUninit};
+
+use crate::{
+    array::{self, Element, InitIter, Place},
+    include::*,
+    tuple::{self, Construct, InElement, Tuple, TupleList},
+    ASender, BSender,
+};
+
+/// The capacity in bytes of the stack storage of a [`Scope`].
+///
+/// Slots that don't fit in the remaining stack storage are allocated by the
+/// global allocator instead.
+pub const CAPACITY: usize = 1024;
+
+/// A scope to create slots in, whose storage lives on the stack.
+///
+/// See [`scope`] for more information.
+#[repr(align(16))]
+pub struct Scope {
+    buffer: cell::UnsafeCell<[MaybeUninit<u8>; CAPACITY]>,
+    offset: AtomicUsize,
+}
+
+impl core::fmt::Debug for Scope {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Scope")
+            .field("offset", &self.offset)
+            .finish_non_exhaustive()
+    }
+}
+
+// SAFETY: The stack storage is only handed out in disjoint regions, which are
+// reserved atomically.
+unsafe impl Sync for Scope {}
+
+impl Scope {
+    fn new() -> Self {
+        Scope {
+            buffer: cell::UnsafeCell::new([MaybeUninit::uninit(); CAPACITY]),
+            offset: AtomicUsize::new(0),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn contains(&self, ptr: NonNull<u8>) -> bool {
+        let base = self.buffer.get().addr();
+        (base..base + CAPACITY).contains(&ptr.as_ptr().addr())
+    }
+
+    /// Create an either slot in this scope.
+    ///
+    /// See [`either`](crate::either()) for more information.
+    pub fn either<A, B>(&self) -> (ASender<A, B, &Self>, BSender<A, B, &Self>) {
+        crate::either_in(self)
+    }
+
+    /// Create an array slot in this scope.
+    ///
+    /// See [`array`](crate::array()) for more information.
+    pub fn array<T, const N: usize>(&self) -> [array::Sender<T, [Element<T>; N], &Self>; N] {
+        array::array_in(self)
+    }
+
+    /// Create an array slot in this scope, whose values will be placed on
+    /// `place`.
+    ///
+    /// See [`from_place`](crate::array::from_place) for more information.
+    pub fn from_place<T, P: Place<T>>(&self, place: P) -> InitIter<T, P, &Self> {
+        array::from_place_in(place, self)
+    }
+
+    /// Create a tuple slot in this scope.
+    ///
+    /// See [`tuple`](crate::tuple()) for more information.
+    pub fn tuple<'scope, T>(
+        &'scope self,
+    ) -> <<T as Construct<&'scope Self>>::Sender as TupleList>::Tuple
+    where
+        T: Construct<&'scope Self>,
+        <T as Tuple>::TupleList: InElement,
+    {
+        tuple::tuple_in::<T, _>(self)
+    }
+}
+
+// SAFETY: The regions handed out are disjoint and live as long as the scope,
+// or are allocated by the global allocator.
+unsafe impl Allocator for Scope {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let base = self.buffer.get().cast::<u8>();
+        let mut offset = self.offset.load(Relaxed);
+        loop {
+            // The regions are synchronized by the slots themselves, so no additional
+            // ordering is needed here.
+            let start = (base.addr() + offset).next_multiple_of(layout.align()) - base.addr();
+            let end = start + layout.size();
+            if end > CAPACITY {
+                #[cfg(feature = "alloc")]
+                break Global.allocate(layout);
+                #[cfg(not(feature = "alloc"))]
+                break Err(AllocError);
+            }
+            match self
+                .offset
+                .compare_exchange_weak(offset, end, Relaxed, Relaxed)
+            {
+                // SAFETY: `start` is in the bounds of the buffer.
+                Ok(_) => {
+                    break Ok(NonNull::slice_from_raw_parts(
+                        unsafe { NonNull::new_unchecked(base.add(start)) },
+                        layout.size(),
+                    ))
+                }
+                Err(current) => offset = current,
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        // Regions on the stack are reclaimed all at once when the scope returns.
+        #[cfg(feature = "alloc")]
+        if !self.contains(ptr) {
+            // SAFETY: The region is allocated by the global allocator.
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+        #[cfg(not(feature = "alloc"))]
+        let _ = (ptr, layout);
+    }
+}
+
+/// Create a scope for slots whose storage lives on the stack of this function.
+///
+/// The slots created by the [`Scope`] borrow it, so every sender must be
+/// consumed or dropped before this function returns, which allows the values
+/// to carry non-`'static` borrows. At most [`CAPACITY`] bytes of slots are
+/// stored on the stack; the rest are allocated by the global allocator.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut data = [1, 2, 3];
+/// let sum = either_slot::scope(|s| {
+///     let senders = s.array::<&mut i32, 3>();
+///     std::thread::scope(|t| {
+///         let handles = senders
+///             .into_iter()
+///             .zip(&mut data)
+///             .map(|(sender, x)| {
+///                 t.spawn(move || {
+///                     *x *= 2;
+///                     sender.send(x)
+///                 })
+///             })
+///             .collect::<Vec<_>>();
+///         let iter = handles
+///             .into_iter()
+///             .find_map(|h| h.join().unwrap().err())
+///             .unwrap();
+///         iter.map(|x| *x).sum::<i32>()
+///     })
+/// });
+/// assert_eq!(sum, 12);
+/// ```
+pub fn scope<R>(f: impl FnOnce(&Scope) -> R) -> R {
+    let scope = Scope::new();
+    f(&scope)
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use alloc::vec::Vec;
+    use std::thread;
+
+    use super::scope;
+    use crate::SendError;
+
+    #[test]
+    fn borrow() {
+        let mut data = [1, 2];
+        scope(|s| {
+            let (a, b) = s.either::<&mut i32, &str>();
+            let [x, y] = &mut data;
+            a.send(x).unwrap();
+            let Err(SendError::Received(_, x)) = b.send("b") else {
+                unreachable!()
+            };
+            *x += 10;
+
+            let (s1, s2) = s.tuple::<(&mut i32, char)>();
+            s1.send(y).unwrap();
+            let (y, c) = s2.send('c').unwrap_err();
+            *y.unwrap() += 20;
+            assert_eq!(c, Some('c'));
+        });
+        assert_eq!(data, [11, 22]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn erased() {
+        let data = [1, 2];
+        scope(|s| {
+            let [s1, s2] = s.array::<&i32, 2>().map(|s| s.erase_send());
+            let mut s3 = crate::vec::<&i32>(1);
+            let senders = [s1, s2, s3.next().unwrap().erase_send()];
+
+            let results = thread::scope(|t| {
+                let j = (senders.into_iter().zip([&data[0], &data[1], &data[0]]))
+                    .map(|(sender, x)| t.spawn(move || sender.send(x).err()))
+                    .collect::<Vec<_>>();
+                j.into_iter()
+                    .filter_map(|j| j.join().unwrap())
+                    .collect::<Vec<_>>()
+            });
+            let values = results.into_iter().flatten().copied().collect::<Vec<_>>();
+            assert_eq!(values, [1, 2, 1]);
+        });
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn fallback() {
+        use super::CAPACITY;
+        use crate::array::Element;
+
+        scope(|s| {
+            let [s1, s2] = s.array::<[u8; CAPACITY], 2>();
+            s1.send([1; CAPACITY]).unwrap();
+            let iter = s2.send([2; CAPACITY]).unwrap_err();
+            assert!(iter.map(|a| a[0]).eq([1, 2]));
+
+            let mut senders = s.from_place(Element::array::<2>());
+            let (s1, s2) = (senders.next().unwrap(), senders.next().unwrap());
+            s1.send(1).unwrap();
+            assert!(s2.send(2).unwrap_err().eq([1, 2]));
+        });
+    }
+
+    #[test]
+    fn send() {
+        let data = [1, 2];
+        scope(|s| {
+            let [s1, s2] = s.array::<&i32, 2>();
+            thread::scope(|t| {
+                let j = t.spawn(|| s1.send(&data[0]));
+                let res = s2.send(&data[1]).and(j.join().unwrap());
+                assert_eq!(res.unwrap_err().copied().collect::<Vec<_>>(), [1, 2]);
+            });
+        })
+    }
+}
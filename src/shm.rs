@@ -0,0 +1,620 @@
+//! Shared-memory slots usable across processes.
+//!
+//! The slots in this module are `#[repr(C)]` and position-independent: they
+//! hold no pointers, so they can live in a `memfd` or `shm_open` mapping,
+//! even if the mapping is placed at different addresses in different
+//! processes. Senders are constructed from the raw mapping plus the index of
+//! the slot in it. A zero-filled slot is vacant, so a freshly created mapping
+//! needs no further initialization.
+//!
+//! Values are copied in and out of the mapping, so they are limited to
+//! [`ShmSafe`] types, which hold no pointers either. The atomics must also be
+//! lock-free across processes, so this module always uses the native atomics
+//! of `core`, regardless of the `portable-atomic` and `critical-section`
+//! features whose atomics only synchronize within one process, and is only
+//! available on targets supporting them.
+
+use core::{
+    cell::UnsafeCell,
+    fmt, hint,
+    iter::FusedIterator,
+    mem::{ManuallyDrop, MaybeUninit},
+    sync::atomic::{self, AtomicBool, AtomicU8, AtomicUsize, Ordering::*},
+};
+
+use crate::{SendError, SlotSender};
+
+/// Plain old data that can be copied between processes through a shared
+/// mapping.
+///
+/// This trait is implemented for the primitive integer and floating-point
+/// types, and arrays of them.
+///
+/// # Safety
+///
+/// The type must be [`Copy`] and hold no pointers, references, handles or any
+/// other state that is only meaningful within one process, e.g. a `#[repr(C)]`
+/// struct of fields that are all `ShmSafe`.
+pub unsafe trait ShmSafe: Copy + Send + Sync + 'static {}
+
+macro_rules! impl_shm_safe {
+    ($($t:ty),* $(,)?) => {$(
+        // SAFETY: Primitive integers and floats are plain old data.
+        unsafe impl ShmSafe for $t {}
+    )*};
+}
+impl_shm_safe!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+// SAFETY: An array of plain old data is plain old data.
+unsafe impl<T: ShmSafe, const N: usize> ShmSafe for [T; N] {}
+
+const INIT: u8 = 0;
+const WRITING: u8 = 1;
+const HAS_A: u8 = 2;
+const HAS_B: u8 = 3;
+const DONE: u8 = 4;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+union Place<A: ShmSafe, B: ShmSafe> {
+    a: A,
+    b: B,
+}
+
+/// A shared-memory [`either`](crate::either()) slot.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::{shm, SendError};
+///
+/// // A stand-in for a shared mapping.
+/// let mut mapping = [shm::EitherSlot::<u32, u8>::new(), shm::EitherSlot::new()];
+/// let base = mapping.as_mut_ptr().cast::<u8>();
+///
+/// // SAFETY: Each sender of slot 1 is created once.
+/// let a = unsafe { shm::ASender::<u32, u8>::from_raw(base, 1) };
+/// let b = unsafe { shm::BSender::<u32, u8>::from_raw(base, 1) };
+/// a.send(1).unwrap();
+/// assert_eq!(b.send(b'x'), Err(SendError::Received(b'x', 1)));
+/// ```
+#[repr(C)]
+pub struct EitherSlot<A: ShmSafe, B: ShmSafe> {
+    state: AtomicU8,
+    place: UnsafeCell<MaybeUninit<Place<A, B>>>,
+}
+
+// SAFETY: The place is only accessed by the slot algorithm.
+unsafe impl<A: ShmSafe, B: ShmSafe> Send for EitherSlot<A, B> {}
+unsafe impl<A: ShmSafe, B: ShmSafe> Sync for EitherSlot<A, B> {}
+
+impl<A: ShmSafe, B: ShmSafe> fmt::Debug for EitherSlot<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EitherSlot").finish_non_exhaustive()
+    }
+}
+
+impl<A: ShmSafe, B: ShmSafe> Default for EitherSlot<A, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: ShmSafe, B: ShmSafe> EitherSlot<A, B> {
+    /// Create a new vacant slot, which is equivalent to a zero-filled one.
+    pub fn new() -> Self {
+        EitherSlot {
+            state: AtomicU8::new(INIT),
+            place: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// See [`ASender::from_raw`].
+    unsafe fn from_raw<'a>(mapping: *mut u8, index: usize) -> &'a Self {
+        let ptr = mapping.cast::<Self>();
+        debug_assert!(ptr.is_aligned(), "the mapping is misaligned");
+        unsafe { &*ptr.add(index) }
+    }
+
+    /// Mark the sender as finished, discarding the value of the other sender if
+    /// any.
+    fn finish(&self) {
+        loop {
+            match self.state.load(Acquire) {
+                WRITING => hint::spin_loop(),
+                state => {
+                    if self
+                        .state
+                        .compare_exchange_weak(state, DONE, AcqRel, Acquire)
+                        .is_ok()
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The sender of `A` of a shared-memory [`EitherSlot`].
+#[derive(Debug)]
+pub struct ASender<'a, A: ShmSafe, B: ShmSafe>(&'a EitherSlot<A, B>);
+
+/// The sender of `B` of a shared-memory [`EitherSlot`].
+#[derive(Debug)]
+pub struct BSender<'a, A: ShmSafe, B: ShmSafe>(&'a EitherSlot<A, B>);
+
+impl<'a, A: ShmSafe, B: ShmSafe> ASender<'a, A, B> {
+    /// Construct the sender of the slot at `index` in `mapping`.
+    ///
+    /// # Safety
+    ///
+    /// - `mapping` must be aligned to [`EitherSlot<A, B>`], and valid for reads
+    ///   and writes of at least `index + 1` slots for `'a`.
+    /// - The slot must be initialized by [`EitherSlot::new`] or zero-filled.
+    /// - At most one `ASender` of the slot may exist among all processes.
+    pub unsafe fn from_raw(mapping: *mut u8, index: usize) -> Self {
+        ASender(unsafe { EitherSlot::from_raw(mapping, index) })
+    }
+
+    /// Place the value into the slot, or obtain the value of the
+    /// [`BSender`] alongside ours if it has placed its value; or get back
+    /// ours if it is dropped.
+    pub fn send(self, a: A) -> Result<(), SendError<A, B>> {
+        let slot = ManuallyDrop::new(self).0;
+        loop {
+            match slot.state.compare_exchange(INIT, WRITING, Acquire, Acquire) {
+                Ok(_) => {
+                    // SAFETY: We have the exclusive access in the `WRITING` state.
+                    unsafe { (*slot.place.get()).write(Place { a }) };
+                    slot.state.store(HAS_A, Release);
+                    break Ok(());
+                }
+                Err(WRITING) => hint::spin_loop(),
+                Err(HAS_B) => {
+                    // SAFETY: The other sender has finished writing `b`.
+                    let b = unsafe { (*slot.place.get()).assume_init().b };
+                    slot.state.store(DONE, Release);
+                    break Err(SendError::Received(a, b));
+                }
+                Err(DONE) => break Err(SendError::Disconnected(a)),
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+}
+
+impl<A: ShmSafe, B: ShmSafe> Drop for ASender<'_, A, B> {
+    fn drop(&mut self) {
+        self.0.finish()
+    }
+}
+
+impl<A: ShmSafe, B: ShmSafe> SlotSender for ASender<'_, A, B> {
+    type Item = A;
+    type Completion = SendError<A, B>;
+
+    fn send(self, item: A) -> Result<(), SendError<A, B>> {
+        ASender::send(self, item)
+    }
+}
+
+impl<'a, A: ShmSafe, B: ShmSafe> BSender<'a, A, B> {
+    /// Construct the sender of the slot at `index` in `mapping`.
+    ///
+    /// # Safety
+    ///
+    /// See [`ASender::from_raw`].
+    pub unsafe fn from_raw(mapping: *mut u8, index: usize) -> Self {
+        BSender(unsafe { EitherSlot::from_raw(mapping, index) })
+    }
+
+    /// Place the value into the slot, or obtain the value of the
+    /// [`ASender`] alongside ours if it has placed its value; or get back
+    /// ours if it is dropped.
+    pub fn send(self, b: B) -> Result<(), SendError<B, A>> {
+        let slot = ManuallyDrop::new(self).0;
+        loop {
+            match slot.state.compare_exchange(INIT, WRITING, Acquire, Acquire) {
+                Ok(_) => {
+                    // SAFETY: We have the exclusive access in the `WRITING` state.
+                    unsafe { (*slot.place.get()).write(Place { b }) };
+                    slot.state.store(HAS_B, Release);
+                    break Ok(());
+                }
+                Err(WRITING) => hint::spin_loop(),
+                Err(HAS_A) => {
+                    // SAFETY: The other sender has finished writing `a`.
+                    let a = unsafe { (*slot.place.get()).assume_init().a };
+                    slot.state.store(DONE, Release);
+                    break Err(SendError::Received(b, a));
+                }
+                Err(DONE) => break Err(SendError::Disconnected(b)),
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+}
+
+impl<A: ShmSafe, B: ShmSafe> Drop for BSender<'_, A, B> {
+    fn drop(&mut self) {
+        self.0.finish()
+    }
+}
+
+impl<A: ShmSafe, B: ShmSafe> SlotSender for BSender<'_, A, B> {
+    type Item = B;
+    type Completion = SendError<B, A>;
+
+    fn send(self, item: B) -> Result<(), SendError<B, A>> {
+        BSender::send(self, item)
+    }
+}
+
+/// An element of a shared-memory [`ArraySlot`], which is vacant if
+/// zero-filled.
+///
+/// Unlike [`array::Element`](crate::array::Element), whose flag is the atomic
+/// selected by the features, the flag is always a native atomic.
+#[repr(C)]
+struct Element<T: ShmSafe> {
+    placed: AtomicBool,
+    storage: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T: ShmSafe> Element<T> {
+    fn new() -> Self {
+        Element {
+            placed: AtomicBool::new(false),
+            storage: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// - The caller must own the element, which holds no value yet.
+    /// - The caller must append a [`Release`] ordering.
+    unsafe fn place(&self, value: T) {
+        unsafe { (*self.storage.get()).write(value) };
+        self.placed.store(true, Relaxed);
+    }
+
+    /// # Safety
+    ///
+    /// All the senders must have finished, and the caller must prepend an
+    /// [`Acquire`] fence.
+    unsafe fn get(&self) -> Option<T> {
+        (self.placed.load(Relaxed)).then(|| unsafe { (*self.storage.get()).assume_init() })
+    }
+}
+
+/// A shared-memory [`array`](crate::array()) slot.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::shm;
+///
+/// // A stand-in for a shared mapping.
+/// let mut mapping = [shm::ArraySlot::<u32, 2>::new()];
+/// let base = mapping.as_mut_ptr().cast::<u8>();
+///
+/// // SAFETY: Each sender of slot 0 is created once.
+/// let [s1, s2] = [0, 1].map(|i| unsafe { shm::Sender::<u32, 2>::from_raw(base, 0, i) });
+/// s1.send(1).unwrap();
+/// let iter = s2.send(2).unwrap_err();
+/// assert_eq!(iter.collect::<Vec<_>>(), [1, 2]);
+/// ```
+#[repr(C)]
+pub struct ArraySlot<T: ShmSafe, const N: usize> {
+    finished: AtomicUsize,
+    place: [Element<T>; N],
+}
+
+// SAFETY: The place is only accessed by the slot algorithm.
+unsafe impl<T: ShmSafe, const N: usize> Send for ArraySlot<T, N> {}
+unsafe impl<T: ShmSafe, const N: usize> Sync for ArraySlot<T, N> {}
+
+impl<T: ShmSafe, const N: usize> fmt::Debug for ArraySlot<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArraySlot").finish_non_exhaustive()
+    }
+}
+
+impl<T: ShmSafe, const N: usize> Default for ArraySlot<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ShmSafe, const N: usize> ArraySlot<T, N> {
+    /// Create a new vacant slot, which is equivalent to a zero-filled one.
+    pub fn new() -> Self {
+        ArraySlot {
+            finished: AtomicUsize::new(0),
+            place: core::array::from_fn(|_| Element::new()),
+        }
+    }
+
+    /// Mark a sender as finished, and return the resulting iterator if it is
+    /// the last one.
+    fn finish(&self) -> Option<Iter<'_, T, N>> {
+        // Count up instead of down, so that a zero-filled slot is vacant.
+        if self.finished.fetch_add(1, Release) + 1 == N {
+            atomic::fence(Acquire);
+            Some(Iter {
+                slot: self,
+                index: 0,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// The sender of a shared-memory [`ArraySlot`].
+#[derive(Debug)]
+pub struct Sender<'a, T: ShmSafe, const N: usize> {
+    slot: &'a ArraySlot<T, N>,
+    index: usize,
+}
+
+impl<'a, T: ShmSafe, const N: usize> Sender<'a, T, N> {
+    /// Construct the sender of the element at `element` in the slot at `index`
+    /// in `mapping`.
+    ///
+    /// # Safety
+    ///
+    /// - `mapping` must be aligned to [`ArraySlot<T, N>`], and valid for reads
+    ///   and writes of at least `index + 1` slots for `'a`.
+    /// - The slot must be initialized by [`ArraySlot::new`] or zero-filled.
+    /// - At most one sender of each element may exist among all processes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `element` is out of bounds.
+    pub unsafe fn from_raw(mapping: *mut u8, index: usize, element: usize) -> Self {
+        assert!(element < N, "the element is out of bounds");
+        let ptr = mapping.cast::<ArraySlot<T, N>>();
+        debug_assert!(ptr.is_aligned(), "the mapping is misaligned");
+        Sender {
+            slot: unsafe { &*ptr.add(index) },
+            index: element,
+        }
+    }
+
+    /// Place the value into the slot, or obtain the resulting iterator if no
+    /// other senders exist any longer.
+    pub fn send(self, value: T) -> Result<(), Iter<'a, T, N>> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: Each sender has its ownership of one `Element` storage, and the
+        // `Release` ordering is provided by `finish`.
+        unsafe { this.slot.place[this.index].place(value) };
+        match this.slot.finish() {
+            Some(iter) => Err(iter),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: ShmSafe, const N: usize> Drop for Sender<'_, T, N> {
+    fn drop(&mut self) {
+        // The values are `Copy`, so they are simply discarded.
+        self.slot.finish();
+    }
+}
+
+impl<'a, T: ShmSafe, const N: usize> SlotSender for Sender<'a, T, N> {
+    type Item = T;
+    type Completion = Iter<'a, T, N>;
+
+    fn send(self, item: T) -> Result<(), Iter<'a, T, N>> {
+        Sender::send(self, item)
+    }
+}
+
+/// The resulting iterator of values that all the senders have placed into a
+/// shared-memory [`ArraySlot`].
+#[derive(Debug)]
+pub struct Iter<'a, T: ShmSafe, const N: usize> {
+    slot: &'a ArraySlot<T, N>,
+    index: usize,
+}
+
+impl<T: ShmSafe, const N: usize> Iterator for Iter<'_, T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(elem) = self.slot.place.get(self.index) {
+            self.index += 1;
+            // SAFETY: All the senders have finished, which is synchronized by `finish`,
+            // and the values are `Copy`.
+            if let Some(data) = unsafe { elem.get() } {
+                return Some(data);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(N - self.index))
+    }
+}
+
+impl<T: ShmSafe, const N: usize> FusedIterator for Iter<'_, T, N> {}
+
+#[cfg(all(test, target_os = "linux", not(loom)))]
+mod tests {
+    use alloc::vec::Vec;
+    use core::{mem, ptr};
+
+    use super::{ASender, ArraySlot, BSender, EitherSlot, Sender};
+    use crate::SendError;
+
+    /// Create a zero-filled memfd of `size` bytes.
+    fn memfd(size: usize) -> i32 {
+        unsafe {
+            let fd = libc::memfd_create(c"either-slot".as_ptr(), 0);
+            assert!(fd >= 0);
+            assert_eq!(libc::ftruncate(fd, size as _), 0);
+            fd
+        }
+    }
+
+    /// Map the memfd, at a different address on every call.
+    fn map(fd: i32, size: usize) -> *mut u8 {
+        unsafe {
+            let ptr = libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            );
+            assert_ne!(ptr, libc::MAP_FAILED);
+            ptr.cast()
+        }
+    }
+
+    /// Run `f` in a forked child, and return its exit code.
+    fn fork(f: impl FnOnce() -> i32) -> libc::pid_t {
+        match unsafe { libc::fork() } {
+            0 => unsafe { libc::_exit(f()) },
+            pid => {
+                assert!(pid > 0);
+                pid
+            }
+        }
+    }
+
+    fn wait(pid: libc::pid_t) -> i32 {
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        libc::WEXITSTATUS(status)
+    }
+
+    #[test]
+    fn fork_either() {
+        const COUNT: usize = 16;
+        let size = COUNT * mem::size_of::<EitherSlot<u32, u8>>();
+        let fd = memfd(size);
+
+        let pid = fork(|| {
+            // The child maps the slots on its own, at a different address.
+            let mapping = map(fd, size);
+            (0..COUNT)
+                .map(|index| {
+                    let a = unsafe { ASender::<u32, u8>::from_raw(mapping, index) };
+                    match a.send(index as u32) {
+                        Ok(()) => 0,
+                        Err(SendError::Received(_, b'x')) => 1,
+                        Err(_) => 100,
+                    }
+                })
+                .sum()
+        });
+
+        let mapping = map(fd, size);
+        let mut received = 0;
+        for index in 0..COUNT {
+            let b = unsafe { BSender::<u32, u8>::from_raw(mapping, index) };
+            match b.send(b'x') {
+                Ok(()) => {}
+                Err(SendError::Received(b'x', a)) => {
+                    assert_eq!(a, index as u32);
+                    received += 1
+                }
+                Err(err) => panic!("unexpected result: {err:?}"),
+            }
+        }
+        assert_eq!(received + wait(pid) as usize, COUNT);
+        unsafe { libc::close(fd) };
+    }
+
+    #[test]
+    fn fork_array() {
+        const N: usize = 4;
+        let size = mem::size_of::<ArraySlot<u64, N>>();
+        let fd = memfd(size);
+
+        let check = |iter: super::Iter<'_, u64, N>| {
+            // The check may run in a forked child, so the values are compared in
+            // place without allocating.
+            let mut values = [u64::MAX; N];
+            values
+                .iter_mut()
+                .zip(iter)
+                .for_each(|(v, value)| *v = value);
+            values.sort_unstable();
+            if values == [0, 10, 20, u64::MAX] {
+                1
+            } else {
+                100
+            }
+        };
+        let mapping = map(fd, size);
+        // The last sender leaves its element empty.
+        drop(unsafe { Sender::<u64, N>::from_raw(mapping, 0, N - 1) });
+
+        let pids = (1..N - 1)
+            .map(|element| {
+                fork(move || {
+                    let mapping = map(fd, size);
+                    let sender = unsafe { Sender::<u64, N>::from_raw(mapping, 0, element) };
+                    match sender.send(element as u64 * 10) {
+                        Ok(()) => 0,
+                        Err(iter) => check(iter),
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let sender = unsafe { Sender::<u64, N>::from_raw(mapping, 0, 0) };
+        let mut last = match sender.send(0) {
+            Ok(()) => 0,
+            Err(iter) => check(iter),
+        };
+        last += pids.into_iter().map(wait).sum::<i32>();
+        assert_eq!(last, 1);
+        unsafe { libc::close(fd) };
+    }
+
+    #[test]
+    fn fork_contended() {
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        // A slot whose elements are placed by 2 processes at the same time, which
+        // catches the atomics that only synchronize within one process.
+        const N: usize = 1 << 16;
+        let slot = mem::size_of::<ArraySlot<u8, N>>();
+        // A barrier after the slot makes both processes start at the same time.
+        let size = slot + mem::size_of::<AtomicUsize>();
+        let fd = memfd(size);
+
+        let send = move |elements: core::ops::Range<usize>| {
+            let mapping = map(fd, size);
+            let barrier = unsafe { &*mapping.add(slot).cast::<AtomicUsize>() };
+            barrier.fetch_add(1, Relaxed);
+            while barrier.load(Relaxed) < 2 {
+                core::hint::spin_loop()
+            }
+            elements.fold(0, |code, element| {
+                let sender = unsafe { Sender::<u8, N>::from_raw(mapping, 0, element) };
+                match sender.send(element as u8) {
+                    Ok(()) => code,
+                    Err(iter) => code + if iter.count() == N { 1 } else { 100 },
+                }
+            })
+        };
+        let pid = fork(move || send(N / 2..N));
+        let completed = send(0..N / 2) + wait(pid);
+        assert_eq!(completed, 1);
+
+        let slot = unsafe { &*map(fd, size).cast::<ArraySlot<u8, N>>() };
+        assert_eq!(slot.finished.load(Relaxed), N);
+        unsafe { libc::close(fd) };
+    }
+}
@@ -1,27 +1,44 @@
+mod many;
 mod utils;
 
-use tuple_list::{Tuple, TupleList};
-
-pub use self::utils::{Concat, Construct, Count, InElement, Index};
-use crate::{array::Element, include::*};
+use core::{marker::PhantomData, mem::MaybeUninit};
+
+#[doc(hidden)]
+pub use self::utils::ConstructList;
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub use self::utils::ForwardList;
+pub use self::{
+    many::{Many, PartSender},
+    utils::{
+        Append, Concat, Const, Construct, Count, CountAt, InElement, Index, Position, Split,
+        ToCount, Tuple, TupleList,
+    },
+};
+#[cfg(feature = "alloc")]
+use crate::forward::{Abandon, Hook, Keep};
+use crate::{array::Element, include::*, SlotSender};
 
 #[derive(Debug)]
-struct Inner<T: InElement> {
+struct Inner<T: InElement, A: Allocator = DefaultAlloc> {
     count: AtomicUsize,
     place: T::Place,
+    /// The allocator of this structure, taken out when deallocating.
+    alloc: ManuallyDrop<A>,
 }
 
-impl<T: InElement> Inner<T> {
+impl<T: InElement, A: Allocator> Inner<T, A> {
     const LAYOUT: Layout = Layout::new::<Self>();
 
-    fn new() -> NonNull<Self> {
-        let memory = match Global.allocate(Self::LAYOUT) {
+    fn new_in(alloc: A) -> NonNull<Self> {
+        let memory = match alloc.allocate(Self::LAYOUT) {
             Ok(memory) => memory.cast::<Self>(),
             Err(_) => handle_alloc_error(Self::LAYOUT),
         };
         let value = Self {
             count: AtomicUsize::new(T::TUPLE_LIST_SIZE),
             place: T::init(),
+            alloc: ManuallyDrop::new(alloc),
         };
         // SAFETY: We own this fresh uninitialized memory whose layout is the same as
         // this type.
@@ -29,6 +46,24 @@ impl<T: InElement> Inner<T> {
         memory
     }
 
+    /// Release a reference count of the slot, and return whether it is the last
+    /// one, which then owns the slot.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold a reference count of the slot and must not use it
+    /// anymore, unless it is the last one.
+    unsafe fn release(this: NonNull<Self>, order: atomic::Ordering) -> bool {
+        // SAFETY: See the contract.
+        let inner = unsafe { this.as_ref() };
+        if inner.count.fetch_sub(1, order) == 1 {
+            // We use `Acquire` fence here to observe other executions of placing values.
+            atomic::fence(Acquire);
+            return true;
+        }
+        false
+    }
+
     /// # Safety
     ///
     /// 1. `this` must own a valid `Inner` uniquely (a.k.a. no other references
@@ -43,9 +78,12 @@ impl<T: InElement> Inner<T> {
         let inner = unsafe { this.as_ref() };
 
         let tuple = unsafe { T::take(&inner.place) }.into_tuple();
+        // SAFETY: See contract 2. The allocator is moved out before the deallocation.
+        let alloc = unsafe { ManuallyDrop::take(&mut (*this.as_ptr()).alloc) };
 
-        // SAFETY: See contract 2.
-        unsafe { Global.deallocate(this.cast(), Self::LAYOUT) };
+        // SAFETY: See contract 2. The slot is deallocated before the values are handed
+        // out, so a panicking destructor of any of them leaks nothing.
+        unsafe { alloc.deallocate(this.cast(), Self::LAYOUT) };
 
         tuple
     }
@@ -56,9 +94,9 @@ pub type Whole<Head, Current, Tail> =
     <<Head as Concat<(Current,)>>::Output as Concat<Tail>>::Output;
 
 /// The whole tuple list of concatenated head, current and tail tuples.
-pub type List<Head, Current, Tail> = <Whole<Head, Current, Tail> as Tuple>::TupleList;
+pub type List<Head, Current, Tail> = <Head as Position<Current, Tail>>::List;
 
-type Ptr<Head, Current, Tail> = NonNull<Inner<List<Head, Current, Tail>>>;
+type Ptr<Head, Current, Tail, A> = NonNull<Inner<List<Head, Current, Tail>, A>>;
 
 /// The storage place of the tuple slot senders.
 pub type Place<Head, Current, Tail> = <List<Head, Current, Tail> as InElement>::Place;
@@ -79,100 +117,227 @@ pub type Take<Head, Current, Tail> = <TakeList<Head, Current, Tail> as TupleList
 ///
 /// The user can only access the slot once by this structure.
 #[derive(Debug)]
-pub struct Sender<Head, Current, Tail>(Ptr<Head, Current, Tail>)
+pub struct Sender<Head, Current, Tail, A = DefaultAlloc>(Ptr<Head, Current, Tail, A>)
 where
-    Head: Concat<(Current,)>,
-    <Head as Concat<(Current,)>>::Output: Concat<Tail>,
-    Tail: Tuple,
-    <Whole<Head, Current, Tail> as Tuple>::TupleList: InElement;
+    A: Allocator,
+    Head: Position<Current, Tail>;
 
 // SAFETY: We satisfy the contract by exposing no reference to any associated
 // function, and provide an atomic algorithm during its access or dropping
 // process, which satisfies the need of `Send`.
-unsafe impl<Head, Current, Tail> Send for Sender<Head, Current, Tail>
+unsafe impl<Head, Current, Tail, A> Send for Sender<Head, Current, Tail, A>
 where
-    Head: Concat<(Current,)> + Send,
+    A: Allocator + Send,
+    Head: Position<Current, Tail> + Send,
     Current: Send,
-    <Head as Concat<(Current,)>>::Output: Concat<Tail>,
-    Tail: Tuple + Send,
-    <Whole<Head, Current, Tail> as Tuple>::TupleList: InElement,
+    Tail: Send,
 {
 }
 
 /// The typenum count of a tuple.
 pub type CountOf<T> = <<T as Tuple>::TupleList as Count>::Count;
 
-impl<Head, Current, Tail> Sender<Head, Current, Tail>
+/// The placer of an tuple slot, targeting the element at index `I` of the
+/// whole tuple `T`.
+///
+/// For example, `SenderAt<(A, B, C, D, E, F), 2>` is the same as
+/// `Sender<(A, B), C, (D, E, F)>`.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// use either_slot::tuple::SenderAt;
+///
+/// struct Worker {
+///     sender: SenderAt<(u8, char, &'static str), 1>,
+/// }
+///
+/// let (s1, s2, s3) = either_slot::tuple::<(u8, char, &str)>();
+/// let worker = Worker { sender: s2 };
+/// s1.send(1).unwrap();
+/// s3.send("3").unwrap();
+/// assert_eq!(worker.sender.send('2'), Err((Some(1), Some('2'), Some("3"))));
+/// # }
+/// ```
+pub type SenderAt<T, const I: usize, A = DefaultAlloc> = Sender<
+    <<T as Tuple>::TupleList as Split<CountAt<I>>>::Head,
+    <<T as Tuple>::TupleList as Split<CountAt<I>>>::Current,
+    <<T as Tuple>::TupleList as Split<CountAt<I>>>::Tail,
+    A,
+>;
+
+impl<Head, Current, Tail, A> Sender<Head, Current, Tail, A>
 where
-    Head: Concat<(Current,)>,
-    <Head as Concat<(Current,)>>::Output: Concat<Tail>,
-    Tail: Tuple,
-    <Whole<Head, Current, Tail> as Tuple>::TupleList: InElement,
+    A: Allocator,
+    Head: Position<Current, Tail>,
 {
+    /// The index of the target element in the whole tuple.
+    pub const INDEX: usize = Head::INDEX;
+
+    /// The length of the whole tuple.
+    pub const LEN: usize = Head::LEN;
+
     /// # Safety
     ///
     /// `inner` must hold a valid immutable reference to `Inner`.
-    unsafe fn new(inner: Ptr<Head, Current, Tail>) -> Self {
+    unsafe fn new(inner: Ptr<Head, Current, Tail, A>) -> Self {
         Sender(inner)
     }
 
+    /// Convert the sender into a raw pointer.
+    ///
+    /// See [`ASender::into_raw`](crate::ASender::into_raw) for more
+    /// information.
+    pub fn into_raw(self) -> *mut () {
+        ManuallyDrop::new(self).0.as_ptr().cast()
+    }
+
+    /// Convert the raw pointer back into the sender.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be obtained from [`Sender::into_raw`] of the same type, and
+    /// converted back only once.
+    pub unsafe fn from_raw(raw: *mut ()) -> Self {
+        unsafe { Sender::new(NonNull::new_unchecked(raw.cast())) }
+    }
+
     /// Place the value into the slot, or obtain the resulting tuple if no
     /// other senders exist any longer.
-    pub fn send(self, value: Current) -> Result<(), Take<Head, Current, Tail>>
-    where
-        <Head as Tuple>::TupleList: Count,
-        Place<Head, Current, Tail>: Index<CountOf<Head>, Output = Element<Current>>,
-    {
+    pub fn send(self, value: Current) -> Result<(), Take<Head, Current, Tail>> {
+        self.send_in_place(|storage| storage.write(value))
+    }
+
+    /// Place the value returned by `f` into the slot, or obtain the resulting
+    /// tuple if no other senders exist any longer.
+    ///
+    /// If `f` panics, no value is placed and this sender is considered dropped.
+    pub fn send_with(self, f: impl FnOnce() -> Current) -> Result<(), Take<Head, Current, Tail>> {
+        self.send_in_place(|storage| storage.write(f()))
+    }
+
+    /// Initialize the value directly in the storage of the slot by `init`, or
+    /// obtain the resulting tuple if no other senders exist any longer.
+    ///
+    /// See [`array::Sender::send_in_place`](crate::array::Sender::send_in_place)
+    /// for more information.
+    pub fn send_in_place(
+        self,
+        init: impl FnOnce(&mut MaybeUninit<Current>) -> &mut Current,
+    ) -> Result<(), Take<Head, Current, Tail>> {
         let pointer = self.0;
         // SAFETY: See contract 1 in `Self::new`.
         let inner = unsafe { pointer.as_ref() };
-        let elem: &Element<Current> = Index::<CountOf<Head>>::index(&inner.place);
+        let elem = Head::element(&inner.place);
 
         // SAFETY: Each sender has its ownership of one `Element` storage in its
         // `inner`, and thus the placing is safe. Besides, the appending `Release`
-        // ordering is supplied.
-        unsafe { elem.place(value) };
-        let fetch_sub = inner.count.fetch_sub(1, Release);
+        // ordering is supplied. If `init` panics, we are dropped with no value placed.
+        unsafe { elem.place_with(init) };
 
         // We don't want to call the dropper anymore because it decreases the reference
         // count once more.
         mem::forget(self);
 
-        if fetch_sub == 1 {
-            // SAFETY: We use `Acquire` fence here to observe other executions of placing
-            // values. And since the reference count is now 0, we owns `inner`, so it can be
-            // dropped, returning the tuple safely.
-            atomic::fence(Acquire);
+        // SAFETY: We hold a reference count, and we don't use `inner` again unless it
+        // is the last one, upon which we owns `inner`, so it can be dropped, returning
+        // the tuple safely.
+        if unsafe { Inner::release(pointer, Release) } {
             return Err(unsafe { Inner::drop_in_place(pointer) });
         }
         Ok(())
     }
 }
 
-impl<Head, Current, Tail> Drop for Sender<Head, Current, Tail>
+impl<Head, Current, Tail, A> Drop for Sender<Head, Current, Tail, A>
 where
-    Head: Concat<(Current,)>,
-    <Head as Concat<(Current,)>>::Output: Concat<Tail>,
-    Tail: Tuple,
-    <Whole<Head, Current, Tail> as Tuple>::TupleList: InElement,
+    A: Allocator,
+    Head: Position<Current, Tail>,
 {
     fn drop(&mut self) {
         let pointer = self.0;
-        // SAFETY: See contract 1 in `Self::new`.
-        let inner = unsafe { pointer.as_ref() };
         // No additional ordering is used because we now have no more
         // observations/modifications to slot values, except...
-        if inner.count.fetch_sub(1, Relaxed) == 1 {
+        if unsafe { Inner::release(pointer, Relaxed) } {
             // SAFETY: ... we now owns our `inner`.
-            atomic::fence(Acquire);
             unsafe { Inner::drop_in_place(pointer) };
         }
     }
 }
 
+impl<Head, Current, Tail, A> SlotSender for Sender<Head, Current, Tail, A>
+where
+    A: Allocator,
+    Head: Position<Current, Tail>,
+{
+    type Item = Current;
+    type Completion = Take<Head, Current, Tail>;
+
+    fn send(self, item: Current) -> Result<(), Take<Head, Current, Tail>> {
+        Sender::send(self, item)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<Head, Current, Tail, A> Abandon for Sender<Head, Current, Tail, A>
+where
+    A: Allocator,
+    Head: Position<Current, Tail>,
+{
+    fn abandon(self) -> Option<Take<Head, Current, Tail>> {
+        let pointer = ManuallyDrop::new(self).0;
+        // SAFETY: The same as `Sender::drop`.
+        unsafe { Inner::release(pointer, Relaxed) }
+            .then(|| unsafe { Inner::drop_in_place(pointer) })
+    }
+}
+
+/// A sender of a named field of a struct slot, which converts the completion of
+/// the slot into the partial result struct `R`.
+///
+/// This type is generated by [`EitherSlot`](crate::EitherSlot) derivations.
+#[derive(Debug)]
+pub struct Named<S, R> {
+    sender: S,
+    marker: PhantomData<fn() -> R>,
+}
+
+impl<S: SlotSender, R: From<S::Completion>> Named<S, R> {
+    #[doc(hidden)]
+    pub fn new(sender: S) -> Self {
+        Named {
+            sender,
+            marker: PhantomData,
+        }
+    }
+
+    /// Place the value into the slot, or obtain the partial result struct if
+    /// no other senders exist any longer.
+    pub fn send(self, value: S::Item) -> Result<(), R> {
+        self.sender.send(value).map_err(R::from)
+    }
+
+    /// Obtain the underlying sender.
+    pub fn into_inner(self) -> S {
+        self.sender
+    }
+}
+
+impl<S: SlotSender, R: From<S::Completion>> SlotSender for Named<S, R> {
+    type Item = S::Item;
+    type Completion = R;
+
+    fn send(self, item: S::Item) -> Result<(), R> {
+        Named::send(self, item)
+    }
+}
+
 /// Create a tuple slot, and return a tuple of senders targeting their own
 /// respective element in the slot.
 ///
+/// The tuple can have at most 32 elements; see [`Tuple`] for details.
+///
 /// # Examples
 ///
 /// ```rust
@@ -190,16 +355,103 @@ where
 /// let ret = s2.send(2).unwrap_err();
 /// assert_eq!(ret, (None, Some(2), Some('3')));
 /// ```
+#[cfg(feature = "alloc")]
 pub fn tuple<T>() -> <T::Sender as TupleList>::Tuple
 where
     T: Construct,
     <T as Tuple>::TupleList: InElement,
 {
-    let inner = Inner::<T::TupleList>::new();
+    tuple_in::<T, _>(Global)
+}
+
+/// Create a tuple slot, whose resulting tuple will be mapped by `map` and sent
+/// by `parent`, and return a tuple of senders targeting their own respective
+/// element in the slot.
+///
+/// See [`array::from_place_forwarding`](crate::array::from_place_forwarding)
+/// for more information.
+///
+/// The parents lead to a [`Root`](crate::forward::Root) created by
+/// [`forward::root`](crate::forward::root), which keeps the completion of the
+/// root slot if a dropped sender completes it.
+///
+/// # Examples
+///
+/// ```rust
+/// let [root, other] = either_slot::array::<String, 2>();
+/// let (root, _orphan) = either_slot::forward::root(root);
+/// let (s1, s2) = either_slot::tuple::tuple_forwarding::<(u8, char), _, _>(root, |(n, c)| {
+///     format!("{n:?} {c:?}")
+/// });
+/// s1.send(1).unwrap();
+/// drop(s2);
+/// let iter = other.send("other".into()).unwrap_err();
+/// assert_eq!(iter.collect::<Vec<_>>(), ["Some(1) None", "other"]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn tuple_forwarding<T, Parent, F>(
+    parent: Parent,
+    map: F,
+) -> <<T::Sender as ForwardList<Parent, F>>::Output as TupleList>::Tuple
+where
+    T: Construct,
+    <T as Tuple>::TupleList: InElement,
+    T::Sender: ForwardList<Parent, F>,
+    Parent: Keep,
+    F: FnOnce(<<<T as Tuple>::TupleList as InElement>::Take as TupleList>::Tuple) -> Parent::Item,
+{
+    tuple_forwarding_in::<T, _, _, _>(parent, map, Global)
+}
+
+/// Create a tuple slot allocated in `alloc`, whose resulting tuple will be
+/// mapped by `map` and sent by `parent`, and return a tuple of senders
+/// targeting their own respective element in the slot.
+///
+/// The hook holding `parent` and `map` is allocated in `alloc` as well. See
+/// [`tuple_forwarding`] for more information.
+#[cfg(feature = "alloc")]
+pub fn tuple_forwarding_in<T, Parent, F, A>(
+    parent: Parent,
+    map: F,
+    alloc: A,
+) -> <<<T as Construct<A>>::Sender as ForwardList<Parent, F, A>>::Output as TupleList>::Tuple
+where
+    T: Construct<A>,
+    <T as Tuple>::TupleList: InElement,
+    <T as Construct<A>>::Sender: ForwardList<Parent, F, A>,
+    Parent: Keep,
+    F: FnOnce(<<<T as Tuple>::TupleList as InElement>::Take as TupleList>::Tuple) -> Parent::Item,
+    A: Allocator + Clone,
+{
+    const {
+        assert!(
+            <T as Tuple>::TupleList::TUPLE_LIST_SIZE > 0,
+            "the slot must not be empty"
+        )
+    }
+    let hook = Hook::new_in(parent, map, alloc.clone());
+    let senders = tuple_in::<T, A>(alloc).into_tuple_list();
+    // SAFETY: `hook` is shared by the senders of this slot only.
+    unsafe { senders.forward(hook) }.into_tuple()
+}
+
+/// Create a tuple slot allocated in `alloc`, and return a tuple of senders
+/// targeting their own respective element in the slot.
+///
+/// See [`tuple()`] for more information.
+pub fn tuple_in<T, A>(alloc: A) -> <<T as Construct<A>>::Sender as TupleList>::Tuple
+where
+    T: Construct<A>,
+    <T as Tuple>::TupleList: InElement,
+    A: Allocator,
+{
+    let inner = Inner::<T::TupleList, A>::new_in(alloc);
+    // SAFETY: `inner` is freshly allocated, and its reference count is exactly the
+    // count of the senders constructed.
     unsafe { T::construct(inner) }.into_tuple()
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     #[cfg(not(loom))]
     use std::thread;
@@ -247,4 +499,154 @@ mod tests {
         #[cfg(loom)]
         loom::model(inner);
     }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn long() {
+        type T = (
+            u8,
+            u16,
+            u32,
+            u64,
+            i8,
+            i16,
+            i32,
+            i64,
+            char,
+            bool,
+            u8,
+            u16,
+            u32,
+            u64,
+            i8,
+            i16,
+            i32,
+            i64,
+            char,
+            bool,
+            u8,
+            u16,
+            u32,
+            u64,
+            i8,
+            i16,
+            i32,
+            i64,
+            char,
+            bool,
+            u8,
+            u16,
+        );
+        let senders = tuple::<T>();
+        assert!(senders.0.send(1).is_ok());
+        assert!(senders.19.send(true).is_ok());
+        assert!(senders.30.send(30).is_ok());
+        drop((
+            senders.1, senders.2, senders.3, senders.4, senders.5, senders.6,
+        ));
+        drop((
+            senders.7, senders.8, senders.9, senders.10, senders.11, senders.12,
+        ));
+        drop((
+            senders.13, senders.14, senders.15, senders.16, senders.17, senders.18,
+        ));
+        drop((
+            senders.20, senders.21, senders.22, senders.23, senders.24, senders.25,
+        ));
+        drop((senders.26, senders.27, senders.28, senders.29));
+
+        let Err(res) = senders.31.send(31) else {
+            panic!("the last sender should obtain the tuple")
+        };
+        assert_eq!(
+            (res.0, res.19, res.30, res.31),
+            (Some(1), Some(true), Some(30), Some(31))
+        );
+        assert_eq!((res.1, res.20, res.29), (None, None, None));
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn position() {
+        use super::{Position, Sender, SenderAt};
+
+        fn place<H: Position<C, T>, C, T>(s: Sender<H, C, T>, value: C) -> (usize, usize) {
+            let ret = (Sender::<H, C, T>::INDEX, Sender::<H, C, T>::LEN);
+            drop(s.send(value));
+            ret
+        }
+
+        let (s1, s2, s3) = tuple::<(u8, char, i32)>();
+        let s2: SenderAt<(u8, char, i32), 1> = s2;
+        assert_eq!(place(s1, 1), (0, 3));
+        assert_eq!(place(s3, 3), (2, 3));
+        assert_eq!(s2.send('2'), Err((Some(1), Some('2'), Some(3))));
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn forward() {
+        use super::tuple_forwarding;
+        use crate::forward;
+
+        let (root, other) = tuple::<(u32, char)>();
+        let (root, _orphan) = forward::root(root);
+        let (s1, s2) = tuple_forwarding::<(u8, u16), _, _>(root, |(a, b)| {
+            u32::from(a.unwrap_or(0)) + u32::from(b.unwrap_or(0))
+        });
+        s2.send(2).unwrap();
+        drop(s1);
+        assert_eq!(other.send('c'), Err((Some(2), Some('c'))));
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn forward_in() {
+        use super::tuple_forwarding_in;
+        use crate::{forward, pool::Pool};
+
+        let pool = Pool::new();
+        let (root, other) = tuple::<(u32, char)>();
+        let (root, _orphan) = forward::root(root);
+        let (s1, s2) = tuple_forwarding_in::<(u8, u16), _, _, _>(
+            root,
+            |(a, b)| u32::from(a.unwrap_or(0)) + u32::from(b.unwrap_or(0)),
+            &pool,
+        );
+        s1.send(1).unwrap();
+        s2.send(2).unwrap();
+        assert_eq!(pool.cached(), 2);
+        assert_eq!(other.send('c'), Err((Some(3), Some('c'))));
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn raw() {
+        use super::Sender;
+
+        let (s1, s2) = tuple::<(u8, char)>();
+        let raw = s2.into_raw();
+        s1.send(1).unwrap();
+        let s2 = unsafe { Sender::<(u8,), char, ()>::from_raw(raw) };
+        assert_eq!(s2.send('2'), Err((Some(1), Some('2'))));
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn drop_panic() {
+        use std::{
+            panic::{self, AssertUnwindSafe},
+            sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        };
+
+        use crate::bomb::Bomb;
+
+        let dropped = AtomicUsize::new(0);
+        let (s1, s2, s3) = tuple::<(Bomb, Bomb, Bomb)>();
+        s1.send(Bomb(true, &dropped)).unwrap();
+        s2.send(Bomb(false, &dropped)).unwrap();
+        let res = panic::catch_unwind(AssertUnwindSafe(move || drop(s3)));
+        assert!(res.is_err());
+        assert_eq!(dropped.load(Relaxed), 2);
+    }
 }
@@ -0,0 +1,348 @@
+//! Composite elements of tuple slots, each placed by multiple senders.
+
+use core::{array, fmt, mem::MaybeUninit};
+
+use super::{Inner, Position, Ptr, Sender, Take};
+#[cfg(feature = "alloc")]
+use crate::forward::{Abandon, Forward, Keep};
+use crate::{array::Element, include::*, SlotSender};
+
+/// A composite element of a tuple slot, holding `N` values of `T` placed by
+/// their own senders.
+///
+/// The sender of this element is split into `N` [`PartSender`]s by
+/// [`Sender::split`], or by [`Forward::split`] for the slots created by
+/// [`tuple_forwarding`](super::tuple_forwarding). The part senders share the
+/// allocation and the reference count of the whole slot. The resulting tuple
+/// then holds `Some` of this element, whose values can be obtained by
+/// [`Many::into_array`]; or `None` if the sender is consumed or dropped before
+/// being split.
+///
+/// Only this type expands into multiple senders. An element of `[T; N]` is an
+/// ordinary element placed as a whole by a single sender, and the resulting
+/// tuple holds `Option<Many<T, N>>` instead of `[Option<T>; N]`, since every
+/// element of the resulting tuple is an `Option` of the element type.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// use either_slot::tuple::Many;
+///
+/// let (config, shards, stats) = either_slot::tuple::<(&str, Many<u32, 3>, u8)>();
+/// let [s1, s2, s3] = shards.split();
+/// config.send("config").unwrap();
+/// s1.send(1).unwrap();
+/// drop(s2);
+/// stats.send(0).unwrap();
+///
+/// let (config, shards, stats) = s3.send(3).unwrap_err();
+/// assert_eq!((config, stats), (Some("config"), Some(0)));
+/// assert_eq!(shards.unwrap().into_array(), [Some(1), None, Some(3)]);
+/// # }
+/// ```
+pub struct Many<T, const N: usize>([Element<T>; N]);
+
+impl<T, const N: usize> Many<T, N> {
+    /// Obtain the values placed by the part senders, or `None`s for those
+    /// dropped.
+    pub fn into_array(self) -> [Option<T>; N] {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: We own the elements, which are no longer accessed, and each of them
+        // is taken only once.
+        this.0.each_ref().map(|elem| unsafe { elem.take() })
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Many<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Many").finish_non_exhaustive()
+    }
+}
+
+impl<T, const N: usize> Drop for Many<T, N> {
+    fn drop(&mut self) {
+        for elem in &self.0 {
+            // SAFETY: We own the elements, and each of them is taken only once.
+            drop(unsafe { elem.take() })
+        }
+    }
+}
+
+impl<Head, T, const N: usize, Tail, A> Sender<Head, Many<T, N>, Tail, A>
+where
+    A: Allocator,
+    Head: Position<Many<T, N>, Tail>,
+{
+    /// Split the sender of the composite element into the senders of its `N`
+    /// values.
+    ///
+    /// See [`Many`] for more information.
+    pub fn split(self) -> [PartSender<Head, T, N, Tail, A>; N] {
+        const { assert!(N > 0, "`Many` must hold at least one value") }
+
+        let pointer = ManuallyDrop::new(self).0;
+        // SAFETY: See contract 1 in `Sender::new`.
+        let inner = unsafe { pointer.as_ref() };
+        // SAFETY: This sender has its ownership of the element storage, which holds no
+        // value yet. The placing happens before the accesses of the part senders,
+        // which are created right here, and before the taking by the `Release`
+        // ordering of their reference count releases.
+        unsafe { Head::element(&inner.place).place(Many(Element::array())) };
+        // The reference count of this sender is taken over by the first part sender.
+        inner.count.fetch_add(N - 1, Relaxed);
+
+        array::from_fn(|index| PartSender {
+            inner: pointer,
+            index,
+        })
+    }
+}
+
+/// The forwarding sender of a value in a composite element.
+#[cfg(feature = "alloc")]
+type ForwardPart<Head, T, const N: usize, Tail, A, Parent, F> =
+    Forward<PartSender<Head, T, N, Tail, A>, Parent, F, A>;
+
+#[cfg(feature = "alloc")]
+impl<Head, T, const N: usize, Tail, A, Parent, F>
+    Forward<Sender<Head, Many<T, N>, Tail, A>, Parent, F, A>
+where
+    A: Allocator,
+    Head: Position<Many<T, N>, Tail>,
+    Parent: Keep,
+    F: FnOnce(Take<Head, Many<T, N>, Tail>) -> Parent::Item,
+{
+    /// Split the forwarding sender of the composite element into the
+    /// forwarding senders of its `N` values.
+    ///
+    /// See [`Many`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use either_slot::tuple::{tuple_forwarding, Many};
+    ///
+    /// let [root, other] = either_slot::array::<u32, 2>();
+    /// let (root, _orphan) = either_slot::forward::root(root);
+    /// let (base, shards) = tuple_forwarding::<(u32, Many<u32, 2>), _, _>(root, |(base, shards)| {
+    ///     let shards = shards.unwrap().into_array();
+    ///     base.unwrap() + shards.into_iter().flatten().sum::<u32>()
+    /// });
+    /// let [s1, s2] = shards.split();
+    /// base.send(100).unwrap();
+    /// s1.send(1).unwrap();
+    /// drop(s2);
+    /// let iter = other.send(0).unwrap_err();
+    /// assert_eq!(iter.collect::<Vec<_>>(), [101, 0]);
+    /// ```
+    pub fn split(self) -> [ForwardPart<Head, T, N, Tail, A, Parent, F>; N] {
+        let (sender, hook) = self.into_parts();
+        // SAFETY: The part senders are the senders of the same child slot, which
+        // `hook` is shared by.
+        sender
+            .split()
+            .map(|part| unsafe { Forward::new(part, hook) })
+    }
+}
+
+/// The placer of a value in a composite element of a tuple slot.
+///
+/// See [`Many`] for more information.
+#[derive(Debug)]
+pub struct PartSender<Head, T, const N: usize, Tail, A = DefaultAlloc>
+where
+    A: Allocator,
+    Head: Position<Many<T, N>, Tail>,
+{
+    inner: Ptr<Head, Many<T, N>, Tail, A>,
+    index: usize,
+}
+
+// SAFETY: The same as `Sender`.
+unsafe impl<Head, T, const N: usize, Tail, A> Send for PartSender<Head, T, N, Tail, A>
+where
+    A: Allocator + Send,
+    Head: Position<Many<T, N>, Tail> + Send,
+    T: Send,
+    Tail: Send,
+{
+}
+
+impl<Head, T, const N: usize, Tail, A> PartSender<Head, T, N, Tail, A>
+where
+    A: Allocator,
+    Head: Position<Many<T, N>, Tail>,
+{
+    /// The index of the target value in the composite element.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Place the value into the slot, or obtain the resulting tuple if no
+    /// other senders exist any longer.
+    pub fn send(self, value: T) -> Result<(), Take<Head, Many<T, N>, Tail>> {
+        self.send_in_place(|storage| storage.write(value))
+    }
+
+    /// Place the value returned by `f` into the slot, or obtain the resulting
+    /// tuple if no other senders exist any longer.
+    ///
+    /// If `f` panics, no value is placed and this sender is considered dropped.
+    pub fn send_with(self, f: impl FnOnce() -> T) -> Result<(), Take<Head, Many<T, N>, Tail>> {
+        self.send_in_place(|storage| storage.write(f()))
+    }
+
+    /// Initialize the value directly in the storage of the slot by `init`, or
+    /// obtain the resulting tuple if no other senders exist any longer.
+    ///
+    /// See [`Sender::send_in_place`] for more information.
+    pub fn send_in_place(
+        self,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> Result<(), Take<Head, Many<T, N>, Tail>> {
+        let pointer = self.inner;
+        // SAFETY: See contract 1 in `Sender::new`.
+        let inner = unsafe { pointer.as_ref() };
+        // SAFETY: The composite element is placed upon splitting, and is not taken
+        // until the last sender releases its reference count.
+        let Some(many) = (unsafe { Head::element(&inner.place).get() }) else {
+            unreachable!("the composite element is placed upon splitting")
+        };
+
+        // SAFETY: Each part sender has its ownership of one `Element` storage in the
+        // composite element. If `init` panics, we are dropped with no value placed.
+        unsafe { many.0[self.index].place_with(init) };
+
+        // We don't want to call the dropper anymore because it decreases the reference
+        // count once more.
+        mem::forget(self);
+
+        // SAFETY: The same as `Sender::send_in_place`.
+        if unsafe { Inner::release(pointer, Release) } {
+            return Err(unsafe { Inner::drop_in_place(pointer) });
+        }
+        Ok(())
+    }
+}
+
+impl<Head, T, const N: usize, Tail, A> Drop for PartSender<Head, T, N, Tail, A>
+where
+    A: Allocator,
+    Head: Position<Many<T, N>, Tail>,
+{
+    fn drop(&mut self) {
+        // SAFETY: The same as `Sender::drop`.
+        if unsafe { Inner::release(self.inner, Relaxed) } {
+            unsafe { Inner::drop_in_place(self.inner) };
+        }
+    }
+}
+
+impl<Head, T, const N: usize, Tail, A> SlotSender for PartSender<Head, T, N, Tail, A>
+where
+    A: Allocator,
+    Head: Position<Many<T, N>, Tail>,
+{
+    type Item = T;
+    type Completion = Take<Head, Many<T, N>, Tail>;
+
+    fn send(self, item: T) -> Result<(), Take<Head, Many<T, N>, Tail>> {
+        PartSender::send(self, item)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<Head, T, const N: usize, Tail, A> Abandon for PartSender<Head, T, N, Tail, A>
+where
+    A: Allocator,
+    Head: Position<Many<T, N>, Tail>,
+{
+    fn abandon(self) -> Option<Take<Head, Many<T, N>, Tail>> {
+        let pointer = ManuallyDrop::new(self).inner;
+        // SAFETY: The same as `Sender::drop`.
+        unsafe { Inner::release(pointer, Relaxed) }
+            .then(|| unsafe { Inner::drop_in_place(pointer) })
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    #[cfg(not(loom))]
+    use std::thread;
+
+    #[cfg(loom)]
+    use loom::thread;
+
+    use super::Many;
+    use crate::tuple::tuple;
+
+    #[test]
+    fn send() {
+        fn inner() {
+            let (s1, s2) = tuple::<(u8, Many<i32, 2>)>();
+            let [p1, p2] = s2.split();
+            let j1 = thread::spawn(|| p1.send(1));
+            let j2 = thread::spawn(|| p2.send(2));
+
+            let res = s1.send(0).and(j1.join().unwrap()).and(j2.join().unwrap());
+            let Err((value, many)) = res else {
+                panic!("the last sender should obtain the tuple")
+            };
+            assert_eq!(value, Some(0));
+            assert_eq!(many.unwrap().into_array(), [Some(1), Some(2)]);
+        }
+
+        #[cfg(not(loom))]
+        inner();
+        #[cfg(loom)]
+        loom::model(inner);
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn forward() {
+        use std::vec::Vec;
+
+        use crate::{array::array, forward, tuple::tuple_forwarding};
+
+        let [root, other] = array::<[Option<u8>; 3], 2>();
+        let (root, _orphan) = forward::root(root);
+        let (s1, s2) = tuple_forwarding::<(Many<u8, 3>, u8), _, _>(root, |(many, _)| {
+            many.unwrap().into_array()
+        });
+        let [p1, p2, p3] = s1.split();
+        assert!(p2.send(2).is_ok());
+        drop((p1, s2));
+        assert!(other.send([None; 3]).is_ok());
+
+        let Err(iter) = p3.send(3) else {
+            panic!("the last part sender should complete the parent")
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            [[None, Some(2), Some(3)], [None; 3]]
+        );
+    }
+
+    #[cfg(not(loom))]
+    #[test]
+    fn unsplit() {
+        use std::{rc::Rc, vec::Vec};
+
+        let (s1, s2) = tuple::<(Rc<()>, Many<Rc<()>, 3>)>();
+        drop(s2);
+        let (value, many) = s1.send(Rc::new(())).unwrap_err();
+        assert!(value.is_some() && many.is_none());
+
+        let rc = Rc::new(());
+        let (s1, s2) = tuple::<(Rc<()>, Many<Rc<()>, 3>)>();
+        let parts = Vec::from(s2.split());
+        drop(s1);
+        let mut res = parts.into_iter().filter_map(|p| p.send(rc.clone()).err());
+        let (_, many) = res.next().unwrap();
+        assert_eq!(Rc::strong_count(&rc), 4);
+        drop(many);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
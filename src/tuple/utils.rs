@@ -1,12 +1,16 @@
 use core::{marker::PhantomData, ptr::NonNull};
 
-use tuple_list::{Tuple, TupleList};
-
-use super::{Element, Inner, Sender};
+use super::{Element, Inner, Sender, Whole};
+#[cfg(feature = "alloc")]
+use crate::forward::{Abandon, Forward, Hook, Keep};
+use crate::{
+    array::Flag,
+    include::{Allocator, AtomicBool, DefaultAlloc},
+};
 
 /// A trait for tuple lists that can be converted into its element storage
-/// place in [`Sender`].
-pub trait InElement: TupleList {
+/// place in [`Sender`], whose elements are flagged by `F`.
+pub trait InElement<F: Flag = AtomicBool>: TupleList {
     #[doc(hidden)]
     type Place: TupleList;
     #[doc(hidden)]
@@ -22,7 +26,7 @@ pub trait InElement: TupleList {
     unsafe fn take(place: &Self::Place) -> Self::Take;
 }
 
-impl InElement for () {
+impl<F: Flag> InElement<F> for () {
     type Place = ();
     fn init() {}
     unsafe fn place(_: &(), _: ()) {}
@@ -30,84 +34,168 @@ impl InElement for () {
     unsafe fn take(_: &()) {}
 }
 
-impl<Head, Tail> InElement for (Head, Tail)
+impl<Head, Tail, F: Flag> InElement<F> for (Head, Tail)
 where
     (Head, Tail): TupleList,
-    Tail: InElement,
-    (Element<Head>, <Tail as InElement>::Place): TupleList,
-    (Option<Head>, <Tail as InElement>::Take): TupleList,
+    Tail: InElement<F>,
+    (Element<Head, F>, <Tail as InElement<F>>::Place): TupleList,
+    (Option<Head>, <Tail as InElement<F>>::Take): TupleList,
 {
-    type Place = (Element<Head>, <Tail as InElement>::Place);
+    type Place = (Element<Head, F>, <Tail as InElement<F>>::Place);
 
     fn init() -> Self::Place {
-        (Element::default(), <Tail as InElement>::init())
+        (Element::default(), <Tail as InElement<F>>::init())
     }
 
     unsafe fn place(place: &Self::Place, data: Self) {
         place.0.place(data.0);
-        <Tail as InElement>::place(&place.1, data.1);
+        <Tail as InElement<F>>::place(&place.1, data.1);
     }
 
-    type Take = (Option<Head>, <Tail as InElement>::Take);
+    type Take = (Option<Head>, <Tail as InElement<F>>::Take);
 
     unsafe fn take(place: &Self::Place) -> Self::Take {
         let head = place.0.take();
-        (head, <Tail as InElement>::take(&place.1))
+        (head, <Tail as InElement<F>>::take(&place.1))
     }
 }
 
-/// A tuple that is concatenable of other tuples.
-pub trait Concat<T: Tuple>: Tuple {
-    /// The concatenated tuple result.
-    type Output: Tuple;
+/// A flat tuple type, convertible into its [`TupleList`].
+///
+/// It is implemented for tuples of up to 32 elements. Since 2.0, this trait
+/// and [`TupleList`] are defined by this crate instead of being re-exported
+/// from `tuple_list`, which only covers 12 elements; bounds naming
+/// `tuple_list::Tuple`, e.g. on [`Concat`], should be switched to this trait.
+pub trait Tuple: Sized {
+    /// The corresponding tuple list type.
+    type TupleList: TupleList<Tuple = Self>;
 
-    /// Concatenates 2 tuples into a longer tuple.
-    fn concat(self, other: T) -> Self::Output;
+    /// Converts the tuple into its tuple list.
+    fn into_tuple_list(self) -> Self::TupleList;
 }
 
-macro_rules! impl_concat {
-    ($($t:ident,)*) => (impl_concat!(@TRANS ($($t,)*), ()););
-    (@TRANS ($head:ident, $($a:ident,)*), ()) => {
-        impl_concat!(@IMPL (), ($head, $($a,)*));
-        impl_concat!(@TRANS ($($a,)*), ());
-    };
-    (@TRANS (), ()) => {
-        impl_concat!(@IMPL (), ());
+/// A tuple list of nested pairs terminated by `()`, e.g. `(A, (B, (C, ())))`,
+/// convertible into its flat [`Tuple`].
+pub trait TupleList: Sized {
+    /// The corresponding flat tuple type.
+    type Tuple: Tuple<TupleList = Self>;
+
+    /// The count of elements in the tuple list.
+    const TUPLE_LIST_SIZE: usize;
+
+    /// Converts the tuple list into its flat tuple.
+    fn into_tuple(self) -> Self::Tuple;
+}
+
+macro_rules! impl_tuple {
+    () => {
+        impl Tuple for () {
+            type TupleList = ();
+
+            fn into_tuple_list(self) {}
+        }
+
+        impl TupleList for () {
+            type Tuple = ();
+
+            const TUPLE_LIST_SIZE: usize = 0;
+
+            fn into_tuple(self) {}
+        }
     };
-    (@IMPL ($($b:ident,)*), ($head:ident, $($a:ident,)*)) => {
-        impl<$head, $($a,)* $($b,)*> Concat<($head, $($a,)*)> for ($($b,)*)
-        where
-            ($head, $($a,)*): Tuple,
-            ($($b,)*): Tuple,
-            ($head, $($a,)* $($b,)*): Tuple,
-        {
-            type Output = ($($b,)* $head, $($a,)* );
+    ($head:ident, $($rest:ident,)*) => {
+        impl<$head, $($rest,)*> Tuple for ($head, $($rest,)*) {
+            type TupleList = impl_tuple!(@LIST $head, $($rest,)*);
 
             #[allow(non_snake_case)]
-            #[allow(unused_parens)]
-            fn concat(self, other: ($head, $($a,)*)) -> Self::Output {
-                let ($head, $($a),*) = other;
-                let ($($b,)*) = self;
-                ($($b,)* $head, $($a,)*)
+            fn into_tuple_list(self) -> Self::TupleList {
+                let ($head, $($rest,)*) = self;
+                impl_tuple!(@LIST $head, $($rest,)*)
             }
         }
-        impl_concat!(@IMPL ($($b,)* $head,), ($($a,)*));
-    };
-    (@IMPL ($($b:ident,)*), ()) => {
-        impl<$($b,)*> Concat<()> for ($($b,)*)
-        where
-            ($($b,)*): Tuple,
-        {
-            type Output = ($($b,)*);
-
-            fn concat(self, _: ()) -> Self::Output {
-                self
+
+        impl<$head, $($rest,)*> TupleList for impl_tuple!(@LIST $head, $($rest,)*) {
+            type Tuple = ($head, $($rest,)*);
+
+            const TUPLE_LIST_SIZE: usize =
+                <impl_tuple!(@LIST $($rest,)*) as TupleList>::TUPLE_LIST_SIZE + 1;
+
+            #[allow(non_snake_case)]
+            fn into_tuple(self) -> Self::Tuple {
+                let impl_tuple!(@LIST $head, $($rest,)*) = self;
+                ($head, $($rest,)*)
             }
         }
+
+        impl_tuple!($($rest,)*);
+    };
+    (@LIST) => (());
+    (@LIST $head:ident, $($rest:ident,)*) => (($head, impl_tuple!(@LIST $($rest,)*)));
+}
+
+// Only one pair of impls is generated for each arity, so supporting longer
+// tuples costs linear code instead of the quadratic one of concatenations.
+// Rust has no variadic tuples, and the trait solving of a slot nests deeper
+// with its length: 64 elements already overflow the default recursion limit of
+// the user crate, so the arity stops at 32, the same as `EitherSlot`.
+impl_tuple!(
+    A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+    A21, A22, A23, A24, A25, A26, A27, A28, A29, A30, A31,
+);
+
+/// A tuple list that is appendable of other tuple lists.
+pub trait Append<T: TupleList>: TupleList {
+    /// The appended tuple list result.
+    type Output: TupleList;
+
+    /// Appends `other` to the end of the tuple list.
+    fn append(self, other: T) -> Self::Output;
+}
+
+impl<T: TupleList> Append<T> for () {
+    type Output = T;
+
+    fn append(self, other: T) -> T {
+        other
     }
 }
 
-impl_concat!(A, B, C, D, E, F, G, H, I, J, K, L,);
+impl<Head, Tail, T> Append<T> for (Head, Tail)
+where
+    (Head, Tail): TupleList,
+    Tail: Append<T>,
+    T: TupleList,
+    (Head, <Tail as Append<T>>::Output): TupleList,
+{
+    type Output = (Head, <Tail as Append<T>>::Output);
+
+    fn append(self, other: T) -> Self::Output {
+        (self.0, self.1.append(other))
+    }
+}
+
+/// A tuple that is concatenable of other tuples.
+pub trait Concat<T: Tuple>: Tuple {
+    /// The concatenated tuple result.
+    type Output: Tuple;
+
+    /// Concatenates 2 tuples into a longer tuple.
+    fn concat(self, other: T) -> Self::Output;
+}
+
+impl<S, T> Concat<T> for S
+where
+    S: Tuple,
+    T: Tuple,
+    S::TupleList: Append<T::TupleList>,
+{
+    type Output = <<S::TupleList as Append<T::TupleList>>::Output as TupleList>::Tuple;
+
+    fn concat(self, other: T) -> Self::Output {
+        let list = self.into_tuple_list();
+        list.append(other.into_tuple_list()).into_tuple()
+    }
+}
 
 pub struct UTerm;
 pub struct UInt<U>(PhantomData<U>);
@@ -165,8 +253,132 @@ where
     }
 }
 
+/// A constant index, convertible into its typenum count by [`ToCount`].
+pub struct Const<const I: usize>;
+
+/// A constant index convertible into its typenum count.
+pub trait ToCount {
+    /// The output typenum count.
+    type Count;
+}
+
+impl ToCount for Const<0> {
+    type Count = UTerm;
+}
+
+macro_rules! impl_to_count {
+    ($prev:literal, $($next:literal,)*) => (impl_to_count!(@IMPL $prev, $($next,)*););
+    (@IMPL $prev:literal, $next:literal, $($rest:literal,)*) => {
+        impl ToCount for Const<$next> {
+            type Count = UInt<<Const<$prev> as ToCount>::Count>;
+        }
+        impl_to_count!(@IMPL $next, $($rest,)*);
+    };
+    (@IMPL $prev:literal,) => {};
+}
+impl_to_count!(
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+    26, 27, 28, 29, 30, 31,
+);
+
+/// The typenum count of a constant index.
+pub type CountAt<const I: usize> = <Const<I> as ToCount>::Count;
+
+/// A tuple list that is splittable at a typenum index into the head tuple, the
+/// current element and the tail tuple.
+///
+/// The type parameter can only receive the output type of [`Count`].
+pub trait Split<I>: TupleList {
+    /// The tuple of elements before the index.
+    type Head: Tuple;
+    /// The element at the index.
+    type Current;
+    /// The tuple of elements after the index.
+    type Tail: Tuple;
+}
+
+impl<Head, Tail> Split<UTerm> for (Head, Tail)
+where
+    Tail: TupleList,
+    (Head, Tail): TupleList,
+{
+    type Head = ();
+    type Current = Head;
+    type Tail = Tail::Tuple;
+}
+
+impl<Head, Tail, U> Split<UInt<U>> for (Head, Tail)
+where
+    Tail: Split<U>,
+    (Head, Tail): TupleList,
+    (Head,): Concat<<Tail as Split<U>>::Head>,
+{
+    type Head = <(Head,) as Concat<<Tail as Split<U>>::Head>>::Output;
+    type Current = <Tail as Split<U>>::Current;
+    type Tail = <Tail as Split<U>>::Tail;
+}
+
+mod sealed {
+    pub trait Sealed<Current, Tail, F> {}
+}
+
+/// The position of a tuple slot sender, implemented by its head tuple, i.e. the
+/// element `Current` following all the elements of `Self` and followed by all
+/// the elements of `Tail`, in a slot whose elements are flagged by `F`.
+///
+/// This trait is sealed and implemented for every valid position, so generic
+/// code over senders only needs to bound on it.
+pub trait Position<Current, Tail, F: Flag = AtomicBool>:
+    Tuple + sealed::Sealed<Current, Tail, F>
+{
+    /// The whole tuple list of the slot.
+    type List: InElement<F>;
+
+    /// The index of the element in the whole tuple.
+    const INDEX: usize;
+
+    /// The length of the whole tuple.
+    const LEN: usize;
+
+    #[doc(hidden)]
+    fn element(place: &<Self::List as InElement<F>>::Place) -> &Element<Current, F>;
+}
+
+impl<Head, Current, Tail, F: Flag> sealed::Sealed<Current, Tail, F> for Head
+where
+    Head: Concat<(Current,)>,
+    <Head as Concat<(Current,)>>::Output: Concat<Tail>,
+    Tail: Tuple,
+    <Whole<Head, Current, Tail> as Tuple>::TupleList: InElement<F>,
+    <Head as Tuple>::TupleList: Count,
+    <<Whole<Head, Current, Tail> as Tuple>::TupleList as InElement<F>>::Place:
+        Index<<<Head as Tuple>::TupleList as Count>::Count, Output = Element<Current, F>>,
+{
+}
+
+impl<Head, Current, Tail, F: Flag> Position<Current, Tail, F> for Head
+where
+    Head: Concat<(Current,)>,
+    <Head as Concat<(Current,)>>::Output: Concat<Tail>,
+    Tail: Tuple,
+    <Whole<Head, Current, Tail> as Tuple>::TupleList: InElement<F>,
+    <Head as Tuple>::TupleList: Count,
+    <<Whole<Head, Current, Tail> as Tuple>::TupleList as InElement<F>>::Place:
+        Index<<<Head as Tuple>::TupleList as Count>::Count, Output = Element<Current, F>>,
+{
+    type List = <Whole<Head, Current, Tail> as Tuple>::TupleList;
+
+    const INDEX: usize = <Head as Tuple>::TupleList::TUPLE_LIST_SIZE;
+
+    const LEN: usize = Self::List::TUPLE_LIST_SIZE;
+
+    fn element(place: &<Self::List as InElement<F>>::Place) -> &Element<Current, F> {
+        Index::<<<Head as Tuple>::TupleList as Count>::Count>::index(place)
+    }
+}
+
 /// A tuple type that is constructible into its tuple slot type.
-pub trait Construct: Tuple
+pub trait Construct<A: Allocator = DefaultAlloc>: Tuple
 where
     Self::TupleList: InElement,
 {
@@ -176,47 +388,93 @@ where
 
     #[allow(private_interfaces)]
     #[doc(hidden)]
-    unsafe fn construct(inner: NonNull<Inner<Self::TupleList>>) -> Self::Sender;
+    unsafe fn construct(inner: NonNull<Inner<Self::TupleList, A>>) -> Self::Sender;
 }
 
-macro_rules! impl_construct {
-    ($($t:ident,)*) => { impl_construct!(@TRANS $($t,)*); };
-    (@TRANS $head:ident, $($rest:ident,)*) => {
-        impl_construct!(@IMPL ($head, $($rest,)*), ($head, $($rest,)*));
-        impl_construct!(@TRANS $($rest,)*);
-    };
-    (@TRANS) => { impl_construct!(@IMPL (), ()); };
-    (@IMPL ($($whole:ident,)*), ($head:ident, $($rest:ident,)*)) => {
-        impl<$($whole,)*> Construct for ($($whole,)*) {
-            type Sender = impl_construct!(@DEF (), ($head, $($rest,)*));
-
-            #[allow(private_interfaces)]
-            unsafe fn construct(inner: NonNull<Inner<Self::TupleList>>) -> Self::Sender {
-                impl_construct!(@INIT inner ($head, $($rest,)*))
-            }
-        }
-    };
-    (@IMPL (), ()) => {
-        impl Construct for () {
-            type Sender = ();
+impl<T, A> Construct<A> for T
+where
+    T: Tuple,
+    T::TupleList: InElement + ConstructList<(), T::TupleList, A>,
+    A: Allocator,
+{
+    type Sender = <T::TupleList as ConstructList<(), T::TupleList, A>>::Sender;
 
-            #[allow(private_interfaces)]
-            unsafe fn construct(_: NonNull<Inner<Self::TupleList>>) {}
-        }
-    };
-    (@DEF ($($prefix:ident,)*), ($current:ident, $($suffix:ident,)*)) => {
-        (
-            Sender<($($prefix,)*), $current, ($($suffix,)*)>,
-            impl_construct!(@DEF ($($prefix,)* $current,), ($($suffix,)*))
-        )
-    };
-    (@DEF ($($prefix:ident,)*), ()) => (());
-    (@INIT $inner:ident ($current:ident, $($suffix:ident,)*)) => {
-        (
-            Sender::new($inner),
-            impl_construct!(@INIT $inner ($($suffix,)*))
-        )
-    };
-    (@INIT $inner:ident ()) => (());
+    #[allow(private_interfaces)]
+    unsafe fn construct(inner: NonNull<Inner<Self::TupleList, A>>) -> Self::Sender {
+        unsafe { <T::TupleList as ConstructList<(), T::TupleList, A>>::construct(inner) }
+    }
+}
+
+/// A tuple list of the elements following `Prefix` in the whole tuple list
+/// `W`, which is constructible into their senders.
+#[doc(hidden)]
+pub trait ConstructList<Prefix, W: InElement, A: Allocator>: TupleList {
+    type Sender: TupleList;
+
+    #[allow(private_interfaces)]
+    unsafe fn construct(inner: NonNull<Inner<W, A>>) -> Self::Sender;
+}
+
+impl<Prefix, W: InElement, A: Allocator> ConstructList<Prefix, W, A> for () {
+    type Sender = ();
+
+    #[allow(private_interfaces)]
+    unsafe fn construct(_: NonNull<Inner<W, A>>) {}
+}
+
+impl<Prefix, Head, Tail, W, A> ConstructList<Prefix, W, A> for (Head, Tail)
+where
+    (Head, Tail): TupleList,
+    Prefix: Position<Head, Tail::Tuple, List = W> + Concat<(Head,)>,
+    Tail: ConstructList<<Prefix as Concat<(Head,)>>::Output, W, A>,
+    W: InElement,
+    A: Allocator,
+    (Sender<Prefix, Head, Tail::Tuple, A>, Tail::Sender): TupleList,
+{
+    type Sender = (Sender<Prefix, Head, Tail::Tuple, A>, Tail::Sender);
+
+    #[allow(private_interfaces)]
+    unsafe fn construct(inner: NonNull<Inner<W, A>>) -> Self::Sender {
+        // SAFETY: The contract is forwarded to the caller.
+        unsafe { (Sender::new(inner), Tail::construct(inner)) }
+    }
+}
+
+/// A tuple list of senders of a tuple slot, which are wrapped into senders
+/// forwarding the completion of the slot by the same hook allocated in `A`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub trait ForwardList<Parent, F, A: Allocator = DefaultAlloc>: TupleList {
+    type Output: TupleList;
+
+    #[allow(private_interfaces)]
+    unsafe fn forward(self, hook: NonNull<Hook<Parent, F, A>>) -> Self::Output;
+}
+
+#[cfg(feature = "alloc")]
+impl<Parent, F, A: Allocator> ForwardList<Parent, F, A> for () {
+    type Output = ();
+
+    #[allow(private_interfaces)]
+    unsafe fn forward(self, _: NonNull<Hook<Parent, F, A>>) {}
+}
+
+#[cfg(feature = "alloc")]
+impl<Head, Tail, Parent, F, A> ForwardList<Parent, F, A> for (Head, Tail)
+where
+    (Head, Tail): TupleList,
+    Head: Abandon,
+    Tail: ForwardList<Parent, F, A>,
+    Parent: Keep,
+    F: FnOnce(Head::Completion) -> Parent::Item,
+    A: Allocator,
+    (Forward<Head, Parent, F, A>, Tail::Output): TupleList,
+{
+    type Output = (Forward<Head, Parent, F, A>, Tail::Output);
+
+    #[allow(private_interfaces)]
+    unsafe fn forward(self, hook: NonNull<Hook<Parent, F, A>>) -> Self::Output {
+        // SAFETY: The contract is forwarded to the caller.
+        unsafe { (Forward::new(self.0, hook), self.1.forward(hook)) }
+    }
 }
-impl_construct!(A, B, C, D, E, F, G, H, I, J, K, L,);
@@ -0,0 +1,393 @@
+//! Allocation-free either slots packed into a single atomic word.
+//!
+//! For small [`Copy`] payloads, e.g. indices, handles and small enums, the
+//! state of the slot and the payload share one atomic word of a [`WordSlot`]
+//! owned by the caller, so sending costs one CAS and no allocation at all.
+
+use core::{fmt, marker::PhantomData, mem::ManuallyDrop};
+
+use crate::{include::*, SendError, SlotSender};
+
+/// The bit marking that a payload is present in the word, above which the
+/// payload is packed.
+const FULL: u64 = 1;
+const TAG_BITS: u32 = 1;
+
+const INIT: u64 = 0;
+/// The state after a sender is dropped without sending, which is not `FULL`.
+const DONE: u64 = 2;
+
+/// A [`Copy`] value that can be packed into the bits of a word.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::word::Word;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Event {
+///     Ready,
+///     Closed,
+/// }
+///
+/// impl Word for Event {
+///     const BITS: u32 = 1;
+///
+///     fn into_bits(self) -> u64 {
+///         self as u64
+///     }
+///
+///     fn from_bits(bits: u64) -> Self {
+///         if bits == 0 { Event::Ready } else { Event::Closed }
+///     }
+/// }
+/// ```
+pub trait Word: Copy {
+    /// The count of bits the value occupies, which must not exceed the
+    /// [payload bits](Storage::PAYLOAD_BITS) of the slot storing it.
+    const BITS: u32;
+
+    /// Convert the value into bits, of which only the lowest [`Word::BITS`]
+    /// are kept.
+    fn into_bits(self) -> u64;
+
+    /// Convert the bits obtained from [`Word::into_bits`] back into the value.
+    fn from_bits(bits: u64) -> Self;
+}
+
+macro_rules! impl_word {
+    ($($t:ty => $u:ty),* $(,)?) => {$(
+        impl Word for $t {
+            const BITS: u32 = <$u>::BITS;
+
+            fn into_bits(self) -> u64 {
+                self as $u as u64
+            }
+
+            fn from_bits(bits: u64) -> Self {
+                bits as $u as $t
+            }
+        }
+    )*};
+}
+impl_word!(u8 => u8, u16 => u16, u32 => u32, i8 => u8, i16 => u16, i32 => u32);
+
+impl Word for () {
+    const BITS: u32 = 0;
+
+    fn into_bits(self) -> u64 {
+        0
+    }
+
+    fn from_bits(_: u64) -> Self {}
+}
+
+impl Word for bool {
+    const BITS: u32 = 1;
+
+    fn into_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits != 0
+    }
+}
+
+impl Word for char {
+    const BITS: u32 = 21;
+
+    fn into_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        char::from_u32(bits as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The unsigned integer type of the atomic word of a [`WordSlot`].
+///
+/// This trait is sealed and implemented for [`u32`], [`u64`] and [`usize`],
+/// whose words hold payloads of up to 31, 63 and `usize::BITS - 1` bits
+/// respectively. [`u64`] is only available on targets with 64-bit atomics, or
+/// with the `critical-section` feature.
+pub trait Storage: sealed::Sealed {
+    #[doc(hidden)]
+    type Atomic: fmt::Debug;
+
+    /// The maximum count of bits of a payload packed into the word.
+    const PAYLOAD_BITS: u32;
+
+    #[doc(hidden)]
+    fn new(bits: u64) -> Self::Atomic;
+
+    #[doc(hidden)]
+    fn compare_exchange(atomic: &Self::Atomic, current: u64, new: u64) -> Result<u64, u64>;
+
+    #[doc(hidden)]
+    fn store(atomic: &Self::Atomic, bits: u64);
+}
+
+macro_rules! impl_storage {
+    ($($(#[$attr:meta])* $t:ty => $atomic:ident),* $(,)?) => {$(
+        $(#[$attr])*
+        impl sealed::Sealed for $t {}
+
+        $(#[$attr])*
+        impl Storage for $t {
+            type Atomic = $atomic;
+
+            const PAYLOAD_BITS: u32 = <$t>::BITS - TAG_BITS;
+
+            fn new(bits: u64) -> $atomic {
+                $atomic::new(bits as $t)
+            }
+
+            fn compare_exchange(atomic: &$atomic, current: u64, new: u64) -> Result<u64, u64> {
+                atomic
+                    .compare_exchange(current as $t, new as $t, AcqRel, Acquire)
+                    .map(|bits| bits as u64)
+                    .map_err(|bits| bits as u64)
+            }
+
+            fn store(atomic: &$atomic, bits: u64) {
+                atomic.store(bits as $t, Release)
+            }
+        }
+
+        $(#[$attr])*
+        impl WordSlot<$t> {
+            const_new! {
+                /// Create a new word-packed either slot.
+                pub fn new() -> Self {
+                    WordSlot($atomic::new(0))
+                }
+            }
+        }
+    )*};
+}
+impl_storage! {
+    #[cfg(any(
+        target_has_atomic = "32",
+        feature = "portable-atomic",
+        feature = "critical-section"
+    ))]
+    u32 => AtomicU32,
+    #[cfg(any(target_has_atomic = "64", feature = "critical-section"))]
+    u64 => AtomicU64,
+    usize => AtomicUsize,
+}
+
+/// The storage of an either slot packed into an atomic word of `W`.
+///
+/// The slot is created by [`either_word`], which borrows the storage mutably
+/// for the lifetime of its senders, so it can only be reused once they are
+/// all consumed or dropped.
+#[derive(Debug)]
+pub struct WordSlot<W: Storage = usize>(W::Atomic);
+
+impl<W: Storage> Default for WordSlot<W> {
+    fn default() -> Self {
+        WordSlot(W::new(INIT))
+    }
+}
+
+fn pack<T: Word>(value: T) -> u64 {
+    let mask = (1 << T::BITS) - 1;
+    ((value.into_bits() & mask) << TAG_BITS) | FULL
+}
+
+fn unpack<T: Word>(word: u64) -> T {
+    T::from_bits(word >> TAG_BITS)
+}
+
+/// Send `value` to `word` in place of one of the senders, receiving the value
+/// of the other one.
+fn send<T: Word, U: Word, W: Storage>(word: &W::Atomic, value: T) -> Result<(), SendError<T, U>> {
+    match W::compare_exchange(word, INIT, pack(value)) {
+        Ok(_) => Ok(()),
+        // Only the other sender can have filled the word.
+        Err(word) if word & FULL != 0 => Err(SendError::Received(value, unpack(word))),
+        Err(_) => Err(SendError::Disconnected(value)),
+    }
+}
+
+/// The sender of `A` of a word-packed either slot.
+///
+/// See [`either_word`] for more information.
+#[derive(Debug)]
+pub struct ASender<'a, A: Word, B: Word, W: Storage = usize> {
+    word: &'a W::Atomic,
+    marker: PhantomData<(A, B)>,
+}
+
+/// The sender of `B` of a word-packed either slot.
+///
+/// See [`either_word`] for more information.
+#[derive(Debug)]
+pub struct BSender<'a, A: Word, B: Word, W: Storage = usize> {
+    word: &'a W::Atomic,
+    marker: PhantomData<(A, B)>,
+}
+
+impl<A: Word, B: Word, W: Storage> ASender<'_, A, B, W> {
+    pub fn send(self, a: A) -> Result<(), SendError<A, B>> {
+        let this = ManuallyDrop::new(self);
+        send::<A, B, W>(this.word, a)
+    }
+}
+
+impl<A: Word, B: Word, W: Storage> Drop for ASender<'_, A, B, W> {
+    fn drop(&mut self) {
+        // The payload is `Copy`, so the value of the other sender, if any, can be
+        // discarded by overwriting.
+        W::store(self.word, DONE)
+    }
+}
+
+impl<A: Word, B: Word, W: Storage> SlotSender for ASender<'_, A, B, W> {
+    type Item = A;
+    type Completion = SendError<A, B>;
+
+    fn send(self, item: A) -> Result<(), SendError<A, B>> {
+        ASender::send(self, item)
+    }
+}
+
+impl<A: Word, B: Word, W: Storage> BSender<'_, A, B, W> {
+    pub fn send(self, b: B) -> Result<(), SendError<B, A>> {
+        let this = ManuallyDrop::new(self);
+        send::<B, A, W>(this.word, b)
+    }
+}
+
+impl<A: Word, B: Word, W: Storage> Drop for BSender<'_, A, B, W> {
+    fn drop(&mut self) {
+        // The same as `ASender::drop`.
+        W::store(self.word, DONE)
+    }
+}
+
+impl<A: Word, B: Word, W: Storage> SlotSender for BSender<'_, A, B, W> {
+    type Item = B;
+    type Completion = SendError<B, A>;
+
+    fn send(self, item: B) -> Result<(), SendError<B, A>> {
+        BSender::send(self, item)
+    }
+}
+
+/// Create an either slot packed into the word of `slot`, which is reset upon
+/// creation.
+///
+/// The senders borrow `slot` mutably, so a slot in use can never be reset.
+/// The payloads must fit in the [payload bits](Storage::PAYLOAD_BITS) of the
+/// word, e.g. 31 bits for `WordSlot<u32>` or 63 bits for `WordSlot<u64>`.
+///
+/// # Examples
+///
+/// ```rust
+/// use either_slot::{
+///     word::{either_word, WordSlot},
+///     SendError,
+/// };
+///
+/// let mut slot = WordSlot::<u32>::new();
+/// let (a, b) = either_word(&mut slot);
+/// a.send(7u16).unwrap();
+/// assert_eq!(b.send('x'), Err(SendError::Received('x', 7)));
+///
+/// let (a, b) = either_word::<u16, char, _>(&mut slot);
+/// drop(a);
+/// assert_eq!(b.send('y'), Err(SendError::Disconnected('y')));
+/// ```
+pub fn either_word<A: Word, B: Word, W: Storage>(
+    slot: &mut WordSlot<W>,
+) -> (ASender<'_, A, B, W>, BSender<'_, A, B, W>) {
+    const {
+        assert!(A::BITS <= W::PAYLOAD_BITS, "`A` does not fit in the word");
+        assert!(B::BITS <= W::PAYLOAD_BITS, "`B` does not fit in the word");
+    }
+    slot.0 = W::new(INIT);
+    let word = &slot.0;
+    let a = ASender {
+        word,
+        marker: PhantomData,
+    };
+    let b = BSender {
+        word,
+        marker: PhantomData,
+    };
+    (a, b)
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::{assert_matches, thread};
+
+    use super::{either_word, Word, WordSlot};
+    use crate::SendError;
+
+    #[test]
+    fn send() {
+        let mut slot = WordSlot::default();
+        for _ in 0..100 {
+            let (a, b) = either_word::<i8, char, usize>(&mut slot);
+            let (r1, r2) = thread::scope(|s| {
+                let j = s.spawn(|| a.send(-1));
+                (b.send('x'), j.join().unwrap())
+            });
+            assert_matches!(
+                (r1, r2),
+                (Ok(()), Err(SendError::Received(-1, 'x')))
+                    | (Err(SendError::Received('x', -1)), Ok(()))
+            );
+        }
+    }
+
+    #[test]
+    fn drop_either() {
+        let mut slot = WordSlot::<u32>::new();
+        let (a, b) = either_word::<bool, (), _>(&mut slot);
+        b.send(()).unwrap();
+        drop(a);
+
+        let (a, b) = either_word::<bool, (), _>(&mut slot);
+        drop(b);
+        assert_eq!(a.send(true), Err(SendError::Disconnected(true)));
+    }
+
+    #[test]
+    fn payload_bits() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Handle(u64);
+
+        impl Word for Handle {
+            const BITS: u32 = 63;
+
+            fn into_bits(self) -> u64 {
+                self.0
+            }
+
+            fn from_bits(bits: u64) -> Self {
+                Handle(bits)
+            }
+        }
+
+        let max = Handle(u64::MAX >> 1);
+        let mut slot = WordSlot::<u64>::new();
+        let (a, b) = either_word(&mut slot);
+        a.send(max).unwrap();
+        assert_eq!(b.send(Handle(0)), Err(SendError::Received(Handle(0), max)));
+
+        let mut slot = WordSlot::<u32>::new();
+        let (a, b) = either_word::<u16, i16, _>(&mut slot);
+        b.send(i16::MIN).unwrap();
+        assert_eq!(a.send(1), Err(SendError::Received(1, i16::MIN)));
+    }
+}